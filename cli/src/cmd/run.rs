@@ -98,7 +98,7 @@ impl Cmd for RunArgs {
         let bytecode = bytecode.expect("No bytecode").object.into_bytes().unwrap();
         let needs_setup = abi.functions().any(|func| func.name == "setUp");
 
-        let mut cfg = crate::utils::sputnik_cfg(&evm_version);
+        let mut cfg = crate::utils::sputnik_cfg(&evm_version)?;
         cfg.create_contract_limit = None;
         let vicinity = evm_opts.vicinity()?;
         let backend = evm_opts.backend(&vicinity)?;
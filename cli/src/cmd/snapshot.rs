@@ -7,26 +7,17 @@ use crate::cmd::{
 };
 use ansi_term::Colour;
 use clap::{Parser, ValueHint};
-use eyre::Context;
-use forge::TestKindGas;
-use once_cell::sync::Lazy;
-use regex::Regex;
+use forge::{
+    gas_snapshot::{read_gas_snapshot_file, write_gas_snapshot_file, SnapshotEntry},
+    TestKindGas,
+};
 use std::{
     cmp::Ordering,
     collections::HashMap,
-    fmt::Write,
-    fs,
-    io::{self, BufRead},
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-/// A regex that matches a basic snapshot entry like
-/// `Test:testDeposit() (gas: 58804)`
-pub static RE_BASIC_SNAPSHOT_ENTRY: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?P<file>(.*?)):(?P<sig>(\w+)\s*\((.*?)\))\s*\(((gas:)?\s*(?P<gas>\d+)|(runs:\s*(?P<runs>\d+),\s*μ:\s*(?P<avg>\d+),\s*~:\s*(?P<med>\d+)))\)").unwrap()
-});
-
 #[derive(Debug, Clone, Parser)]
 pub struct SnapshotArgs {
     /// All test arguments are supported
@@ -72,11 +63,11 @@ impl Cmd for SnapshotArgs {
 
         if let Some(path) = self.diff {
             let snap = path.as_ref().unwrap_or(&self.snap);
-            let snaps = read_snapshot(snap)?;
+            let snaps = read_gas_snapshot_file(snap)?;
             diff(tests, snaps)?;
         } else if let Some(path) = self.check {
             let snap = path.as_ref().unwrap_or(&self.snap);
-            let snaps = read_snapshot(snap)?;
+            let snaps = read_gas_snapshot_file(snap)?;
             if check(tests, snaps) {
                 std::process::exit(0)
             } else {
@@ -150,87 +141,20 @@ impl SnapshotConfig {
     }
 }
 
-/// A general entry in a snapshot file
-///
-/// Has the form `<signature>(gas:? 40181)`
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct SnapshotEntry {
-    pub contract_name: String,
-    pub signature: String,
-    pub gas_used: TestKindGas,
-}
-
-impl FromStr for SnapshotEntry {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        RE_BASIC_SNAPSHOT_ENTRY
-            .captures(s)
-            .and_then(|cap| {
-                cap.name("file").and_then(|file| {
-                    cap.name("sig").and_then(|sig| {
-                        if let Some(gas) = cap.name("gas") {
-                            Some(SnapshotEntry {
-                                contract_name: file.as_str().to_string(),
-                                signature: sig.as_str().to_string(),
-                                gas_used: TestKindGas::Standard(gas.as_str().parse().unwrap()),
-                            })
-                        } else {
-                            cap.name("runs")
-                                .and_then(|runs| {
-                                    cap.name("avg")
-                                        .and_then(|avg| cap.name("med").map(|med| (runs, avg, med)))
-                                })
-                                .map(|(runs, avg, med)| SnapshotEntry {
-                                    contract_name: file.as_str().to_string(),
-                                    signature: sig.as_str().to_string(),
-                                    gas_used: TestKindGas::Fuzz {
-                                        runs: runs.as_str().parse().unwrap(),
-                                        median: med.as_str().parse().unwrap(),
-                                        mean: avg.as_str().parse().unwrap(),
-                                    },
-                                })
-                        }
-                    })
-                })
-            })
-            .ok_or_else(|| format!("Could not extract Snapshot Entry for {}", s))
-    }
-}
-
-/// Reads a list of snapshot entries from a snapshot file
-fn read_snapshot(path: impl AsRef<Path>) -> eyre::Result<Vec<SnapshotEntry>> {
-    let path = path.as_ref();
-    let mut entries = Vec::new();
-    for line in io::BufReader::new(
-        fs::File::open(path)
-            .wrap_err(format!("failed to read snapshot file \"{}\"", path.display()))?,
-    )
-    .lines()
-    {
-        entries
-            .push(SnapshotEntry::from_str(line?.as_str()).map_err(|err| eyre::eyre!("{}", err))?);
-    }
-    Ok(entries)
-}
-
 /// Writes a series of tests to a snapshot file
 fn write_to_snapshot_file(
     tests: &[Test],
     path: impl AsRef<Path>,
     _format: Option<Format>,
 ) -> eyre::Result<()> {
-    let mut out = String::new();
-    for test in tests {
-        writeln!(
-            out,
-            "{}:{} {}",
-            test.contract_name(),
-            test.signature,
-            test.result.kind.gas_used()
-        )?;
-    }
-    Ok(fs::write(path, out)?)
+    write_gas_snapshot_file(
+        path,
+        tests.iter().map(|test| SnapshotEntry {
+            contract_name: test.contract_name().to_string(),
+            signature: test.signature.clone(),
+            gas_used: test.result.kind.gas_used(),
+        }),
+    )
 }
 
 /// A Snapshot entry diff
@@ -363,36 +287,3 @@ fn fmt_change(change: i128) -> String {
         Ordering::Greater => Colour::Red.paint(format!("{}", change)).to_string(),
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn can_parse_basic_snapshot_entry() {
-        let s = "Test:deposit() (gas: 7222)";
-        let entry = SnapshotEntry::from_str(s).unwrap();
-        assert_eq!(
-            entry,
-            SnapshotEntry {
-                contract_name: "Test".to_string(),
-                signature: "deposit()".to_string(),
-                gas_used: TestKindGas::Standard(7222)
-            }
-        );
-    }
-
-    #[test]
-    fn can_parse_fuzz_snapshot_entry() {
-        let s = "Test:deposit() (runs: 256, μ: 100, ~:200)";
-        let entry = SnapshotEntry::from_str(s).unwrap();
-        assert_eq!(
-            entry,
-            SnapshotEntry {
-                contract_name: "Test".to_string(),
-                signature: "deposit()".to_string(),
-                gas_used: TestKindGas::Fuzz { runs: 256, median: 200, mean: 100 }
-            }
-        );
-    }
-}
@@ -11,7 +11,10 @@ use ethers::solc::{ArtifactOutput, ProjectCompileOutput};
 use evm_adapters::{
     call_tracing::ExecutionInfo, evm_opts::EvmOpts, gas_report::GasReport, sputnik::helpers::vm,
 };
-use forge::{MultiContractRunnerBuilder, TestFilter, TestResult};
+use forge::{
+    format::{JsonFormatter, OutputFormatter, PrettyFormatter},
+    MultiContractRunnerBuilder, TestFilter, TestResult,
+};
 use foundry_config::{figment::Figment, Config};
 use regex::Regex;
 use std::{collections::BTreeMap, str::FromStr, sync::mpsc::channel, thread};
@@ -187,7 +190,7 @@ impl Cmd for TestArgs {
         let output = super::compile(&project, false, false)?;
 
         // prepare the test builder
-        let mut evm_cfg = crate::utils::sputnik_cfg(&config.evm_version);
+        let mut evm_cfg = crate::utils::sputnik_cfg(&config.evm_version)?;
         evm_cfg.create_contract_limit = None;
 
         let builder = MultiContractRunnerBuilder::default()
@@ -301,26 +304,7 @@ impl TestOutcome {
 }
 
 fn short_test_result(name: &str, result: &forge::TestResult) {
-    let status = if result.success {
-        Colour::Green.paint("[PASS]")
-    } else {
-        let txt = match (&result.reason, &result.counterexample) {
-            (Some(ref reason), Some(ref counterexample)) => {
-                format!("[FAIL. Reason: {}. Counterexample: {}]", reason, counterexample)
-            }
-            (None, Some(ref counterexample)) => {
-                format!("[FAIL. Counterexample: {}]", counterexample)
-            }
-            (Some(ref reason), None) => {
-                format!("[FAIL. Reason: {}]", reason)
-            }
-            (None, None) => "[FAIL]".to_string(),
-        };
-
-        Colour::Red.paint(txt)
-    };
-
-    println!("{} {} {}", status, name, result.kind.gas_used());
+    println!("{}", PrettyFormatter.fmt_test_result("", name, result));
 }
 
 /// Runs all the tests
@@ -343,8 +327,7 @@ fn test<A: ArtifactOutput + 'static>(
 
     if json {
         let results = runner.test(&filter, None)?;
-        let res = serde_json::to_string(&results)?; // TODO: Make this work normally
-        println!("{}", res);
+        println!("{}", JsonFormatter.fmt_test_results(&results));
         Ok(TestOutcome::new(results, allow_failure))
     } else {
         // Dapptools-style printing of test results
@@ -450,7 +433,7 @@ fn test<A: ArtifactOutput + 'static>(
                 }
             }
             gas_report.finalize();
-            println!("{}", gas_report);
+            println!("{}", PrettyFormatter.fmt_gas_report(&gas_report));
         }
         Ok(TestOutcome::new(results, allow_failure))
     }
@@ -58,14 +58,18 @@ pub fn subscriber() {
         .init();
 }
 
+/// Returns the sputnik config of the hardfork, if sputnik supports it
 #[cfg(feature = "sputnik-evm")]
-pub fn sputnik_cfg(evm: &EvmVersion) -> Config {
-    match evm {
+pub fn sputnik_cfg(evm: &EvmVersion) -> eyre::Result<Config> {
+    Ok(match evm {
         EvmVersion::Istanbul => Config::istanbul(),
         EvmVersion::Berlin => Config::berlin(),
         EvmVersion::London => Config::london(),
-        _ => panic!("Unsupported EVM version"),
-    }
+        other => eyre::bail!(
+            "EVM version `{}` is not supported, use one of `istanbul`, `berlin` or `london`",
+            other
+        ),
+    })
 }
 
 /// Securely reads a secret from stdin, or proceeds to return a fallback value
@@ -172,4 +176,12 @@ mod tests {
         let p = Path::new("contracts/Greeter.sol");
         assert!(!p.is_sol_test());
     }
+
+    #[test]
+    #[cfg(feature = "sputnik-evm")]
+    fn sputnik_cfg_of_hardfork() {
+        assert!(sputnik_cfg(&EvmVersion::London).unwrap().has_base_fee);
+        assert!(!sputnik_cfg(&EvmVersion::Berlin).unwrap().has_base_fee);
+        assert!(sputnik_cfg(&EvmVersion::Byzantium).is_err());
+    }
 }
@@ -65,6 +65,12 @@ where
         Ok((nonce, balance, code))
     }
 
+    /// Gets the hash of the specified block, if it exists.
+    pub fn get_block_hash(&self, number: u64) -> Result<Option<H256>, M::Error> {
+        let block = self.block_on(self.provider.get_block(number))?;
+        Ok(block.and_then(|block| block.hash))
+    }
+
     /// Gets the current block number.
     pub fn get_block_number(&self) -> Result<U64, M::Error> {
         self.block_on(self.provider.get_block_number())
@@ -144,6 +144,19 @@ impl CallTraceArena {
         }
     }
 
+    /// Sums up the gas of all calls in the arena by the selector they called, so that calling
+    /// the same function several times accumulates its gas. Creations and calls without a
+    /// selector are left out.
+    pub fn gas_by_selector(&self) -> BTreeMap<[u8; 4], u64> {
+        let mut gas = BTreeMap::new();
+        for node in self.arena.iter().filter(|node| !node.trace.created) {
+            if let Some(selector) = node.trace.data.get(..4) {
+                *gas.entry(selector.try_into().unwrap()).or_default() += node.trace.cost;
+            }
+        }
+        gas
+    }
+
     /// Updates the values in the calltrace held by the arena based on the passed in trace
     pub fn update(&mut self, trace: CallTrace) {
         let node = &mut self.arena[trace.idx];
@@ -173,6 +173,61 @@ pub struct Env {
 }
 
 impl Env {
+    #[must_use]
+    pub fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    /// Lets every call use as much gas as it needs, for gas heavy tests which would otherwise
+    /// run out of gas.
+    ///
+    /// Calls are not checked against the block gas limit, but are given `gas_limit` gas, so
+    /// this raises `gas_limit` to the maximum instead. The `block.gaslimit` value is left
+    /// unchanged.
+    #[must_use]
+    pub fn disable_block_gas_limit(mut self) -> Self {
+        self.block_gas_limit = Some(self.block_gas_limit.unwrap_or(self.gas_limit));
+        self.gas_limit = u64::MAX;
+        self
+    }
+
+    #[must_use]
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    #[must_use]
+    pub fn with_gas_price(mut self, gas_price: u64) -> Self {
+        self.gas_price = gas_price;
+        self
+    }
+
+    #[must_use]
+    pub fn with_block_base_fee_per_gas(mut self, block_base_fee_per_gas: u64) -> Self {
+        self.block_base_fee_per_gas = block_base_fee_per_gas;
+        self
+    }
+
+    #[must_use]
+    pub fn with_block_coinbase(mut self, block_coinbase: Address) -> Self {
+        self.block_coinbase = block_coinbase;
+        self
+    }
+
+    #[must_use]
+    pub fn with_block_timestamp(mut self, block_timestamp: u64) -> Self {
+        self.block_timestamp = block_timestamp;
+        self
+    }
+
+    #[must_use]
+    pub fn with_block_number(mut self, block_number: u64) -> Self {
+        self.block_number = block_number;
+        self
+    }
+
     #[cfg(feature = "sputnik")]
     pub fn sputnik_state(&self) -> MemoryVicinity {
         MemoryVicinity {
@@ -191,3 +246,78 @@ impl Env {
         }
     }
 }
+
+#[cfg(all(test, feature = "sputnik"))]
+mod tests {
+    use super::*;
+    use crate::{
+        sputnik::{helpers::new_backend, Executor, PRECOMPILES_MAP},
+        test_helpers::COMPILED,
+        Evm,
+    };
+    use ethers::{
+        abi::{ParamType, Token},
+        utils::id,
+    };
+    use sputnik::{Config, ExitReason};
+
+    #[test]
+    fn block_env() {
+        let coinbase = Address::random();
+        let env = Env { gas_limit: 30_000_000, ..Default::default() }
+            .with_block_number(10)
+            .with_block_timestamp(20)
+            .with_block_coinbase(coinbase)
+            .with_block_base_fee_per_gas(30)
+            .with_gas_price(40)
+            .with_chain_id(50);
+        let vicinity = env.sputnik_state();
+        let backend = new_backend(&vicinity, Default::default());
+        let cfg = Config::london();
+        let mut evm = Executor::new(env.gas_limit, &cfg, &backend, &*PRECOMPILES_MAP);
+
+        let compiled = COMPILED.find("BlockEnv").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let (retdata, _, _, _) = evm
+            .call_raw(Address::zero(), addr, id("env()").to_vec().into(), 0.into(), true)
+            .unwrap();
+
+        let uint = ParamType::Uint(256);
+        let types =
+            [uint.clone(), uint.clone(), ParamType::Address, uint.clone(), uint.clone(), uint];
+        let tokens = ethers::abi::decode(&types, &retdata).unwrap();
+        let uint = |x: u64| Token::Uint(x.into());
+        assert_eq!(
+            tokens,
+            vec![uint(10), uint(20), Token::Address(coinbase), uint(30), uint(40), uint(50)]
+        );
+    }
+
+    #[test]
+    fn disable_block_gas_limit() {
+        let setup = |env: Env| {
+            let vicinity = env.sputnik_state();
+            let backend = new_backend(&vicinity, Default::default());
+            let cfg = Config::london();
+            let mut evm = Executor::new(env.gas_limit, &cfg, &backend, &*PRECOMPILES_MAP);
+
+            let compiled = COMPILED.find("HeavySetup").expect("could not find contract");
+            let (addr, _, _, _) = evm
+                .deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+                .unwrap();
+            let (_, status, _, _) = evm
+                .call_raw(Address::zero(), addr, id("setUp()").to_vec().into(), 0.into(), false)
+                .unwrap();
+            (status, vicinity.block_gas_limit)
+        };
+
+        let env = Env::default().with_gas_limit(1_000_000);
+        let (status, _) = setup(env.clone());
+        assert!(matches!(status, ExitReason::Error(_)));
+
+        let (status, block_gas_limit) = setup(env.disable_block_gas_limit());
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(block_gas_limit, 1_000_000.into());
+    }
+}
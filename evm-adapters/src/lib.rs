@@ -14,20 +14,22 @@ pub mod fuzz;
 
 pub mod call_tracing;
 
+pub mod trace_fixture;
+
 pub mod gas_report;
 
 /// Helpers for easily constructing EVM objects.
 pub mod evm_opts;
 
 use ethers::{
-    abi::{Abi, Detokenize, Tokenize},
+    abi::{Abi, Constructor, Detokenize, ParamType, Tokenize},
     contract::{decode_function_data, encode_function_data},
     core::types::{Address, Bytes, U256},
 };
 
 use foundry_utils::IntoFunction;
 
-use eyre::Result;
+use eyre::{Result, WrapErr};
 use once_cell::sync::Lazy;
 
 pub const ASSUME_MAGIC_RETURN_CODE: &[u8] = "FOUNDRY::ASSUME".as_bytes();
@@ -42,7 +44,32 @@ pub enum EvmError {
     #[error("Execution reverted: {reason}, (gas: {gas_used})")]
     // TODO: Add proper log printing.
     /// Error which occurred during execution of an EVM transaction
-    Execution { reason: String, gas_used: u64, logs: Vec<String> },
+    ///
+    /// The raw revert data is kept in `revert_data` for callers which need to decode it
+    /// themselves, e.g. for custom errors
+    Execution { reason: String, gas_used: u64, logs: Vec<String>, revert_data: Option<Bytes> },
+    #[error("nonce too low: expected {expected}, got {provided}")]
+    /// The transaction's nonce has already been used by the sender
+    NonceTooLow { expected: U256, provided: U256 },
+    #[error("nonce too high: expected {expected}, got {provided}")]
+    /// The transaction's nonce skips over the sender's next nonce
+    NonceTooHigh { expected: U256, provided: U256 },
+    #[error("setUp() ran out of gas (gas limit: {gas_limit})")]
+    /// The `setUp()` call exhausted the gas it was given
+    SetupOutOfGas { gas_limit: u64, logs: Vec<String> },
+    #[error("call to account with no code at {address:?}")]
+    /// A call which expected return data was made to an account without code
+    NoCode { address: Address },
+    #[error("return value mismatch: `left != right`\n  left: `{actual}`,\n right: `{expected}`")]
+    /// The decoded return value of a call differs from the expected one, see
+    /// [`call_assert_eq`](Evm::call_assert_eq)
+    ReturnMismatch { actual: String, expected: String },
+    #[error("init code size of {size} bytes exceeds the limit of {limit} bytes")]
+    /// The init code of a deployment is larger than allowed by EIP-3860
+    InitCodeSizeExceeded { size: usize, limit: usize },
+    #[error("execution exceeded the step limit of {limit} opcodes")]
+    /// The transaction was halted for executing more opcodes than allowed
+    StepLimitExceeded { limit: usize, logs: Vec<String> },
     #[error(transparent)]
     /// Error which occurred during ABI encoding / decoding of data
     AbiError(#[from] ethers::contract::AbiError),
@@ -51,6 +78,28 @@ pub enum EvmError {
     Eyre(#[from] eyre::Error),
 }
 
+/// A successful deployment, as returned by [`try_deploy`](Evm::try_deploy)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeployResult<R> {
+    /// The address of the deployed contract
+    pub address: Address,
+    pub status: R,
+    pub gas_used: u64,
+    pub logs: Vec<String>,
+}
+
+/// The outcome of a test contract's `setUp()`, as returned by
+/// [`setup_with_abi`](Evm::setup_with_abi)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetupResult {
+    /// The contract has no `setUp()` function, so it was not called
+    NotPresent,
+    /// `setUp()` succeeded
+    Success { logs: Vec<String> },
+    /// `setUp()` reverted or ran out of gas
+    Reverted { reason: String, logs: Vec<String> },
+}
+
 // TODO: Any reason this should be an async trait?
 /// Low-level abstraction layer for interfacing with various EVMs. Once instantiated, one
 /// only needs to specify the transaction parameters
@@ -68,6 +117,11 @@ pub trait Evm<State> {
     /// Whether a return reason should be considered failing
     fn is_fail(reason: &Self::ReturnReason) -> bool;
 
+    /// Describes a failing return reason, for failures without revert data to decode
+    fn describe_failure(reason: &Self::ReturnReason) -> String {
+        format!("{:?}", reason)
+    }
+
     /// Sets the provided contract bytecode at the corresponding addresses
     fn initialize_contracts<I: IntoIterator<Item = (Address, Bytes)>>(&mut self, contracts: I);
 
@@ -95,6 +149,12 @@ pub trait Evm<State> {
     /// Gets all logs from the execution, regardless of reverts
     fn all_logs(&self) -> Vec<String>;
 
+    /// Describes the operation which made the last call revert with an arithmetic
+    /// over/underflow, if the EVM tracked it
+    fn arithmetic_overflow(&self) -> Option<String> {
+        None
+    }
+
     /// Performs a [`call_unchecked`](Self::call_unchecked), checks if execution reverted, and
     /// proceeds to return the decoded response to the user.
     fn call<D: Detokenize, T: Tokenize, F: IntoFunction>(
@@ -106,17 +166,115 @@ pub trait Evm<State> {
         value: U256,
         abi: Option<&Abi>,
     ) -> std::result::Result<(D, Self::ReturnReason, u64, Vec<String>), EvmError> {
+        self.call_full(from, to, func, args, value, abi).map(|(res, _)| res)
+    }
+
+    /// Performs a [`call`](Self::call), additionally returning the raw return data, for callers
+    /// which need both the decoded and the raw output without executing twice.
+    fn call_full<D: Detokenize, T: Tokenize, F: IntoFunction>(
+        &mut self,
+        from: Address,
+        to: Address,
+        func: F,
+        args: T,
+        value: U256,
+        abi: Option<&Abi>,
+    ) -> std::result::Result<((D, Self::ReturnReason, u64, Vec<String>), Bytes), EvmError> {
         let func = func.into();
-        let (retdata, status, gas, logs) = self.call_unchecked(from, to, &func, args, value)?;
-        if Self::is_fail(&status) {
-            // try to decode the revert reason, else default to the revert status error.
-            let reason = foundry_utils::decode_revert(retdata.as_ref(), abi)
-                .unwrap_or_else(|_| format!("{:?}", status));
-            Err(EvmError::Execution { reason, gas_used: gas, logs })
-        } else {
-            let retdata = decode_function_data(&func, retdata, false)?;
-            Ok((retdata, status, gas, logs))
+        let res = self.call_unchecked(from, to, &func, args, value)?;
+        let (retdata, status, gas, logs) =
+            check_call_result(self, to, res, abi, !func.outputs.is_empty())?;
+        let decoded = decode_function_data(&func, retdata.clone(), false)?;
+        Ok(((decoded, status, gas, logs), retdata))
+    }
+
+    /// Performs a [`call`](Self::call) and compares the decoded return value to `expected`,
+    /// returning an [`EvmError::ReturnMismatch`] if they differ. If the call reverted, the
+    /// [`EvmError::Execution`] with the decoded revert reason is returned instead.
+    #[allow(clippy::too_many_arguments)]
+    fn call_assert_eq<D, T, F>(
+        &mut self,
+        from: Address,
+        to: Address,
+        func: F,
+        args: T,
+        value: U256,
+        abi: Option<&Abi>,
+        expected: D,
+    ) -> std::result::Result<(D, Self::ReturnReason, u64, Vec<String>), EvmError>
+    where
+        D: Detokenize + PartialEq + std::fmt::Debug,
+        T: Tokenize,
+        F: IntoFunction,
+    {
+        let res = self.call::<D, _, _>(from, to, func, args, value, abi)?;
+        if res.0 != expected {
+            return Err(EvmError::ReturnMismatch {
+                actual: format!("{:?}", res.0),
+                expected: format!("{:?}", expected),
+            })
+        }
+        Ok(res)
+    }
+
+    /// Performs a [`call`](Self::call) without sending any value and without an ABI for decoding
+    /// revert reasons
+    fn call_no_value<D: Detokenize, T: Tokenize, F: IntoFunction>(
+        &mut self,
+        from: Address,
+        to: Address,
+        func: F,
+        args: T,
+    ) -> std::result::Result<(D, Self::ReturnReason, u64, Vec<String>), EvmError> {
+        self.call(from, to, func, args, 0.into(), None)
+    }
+
+    /// Like [`call`](Self::call), but for when only the function's selector and parameter types
+    /// are known, e.g. when interacting with an interface whose full ABI is not available.
+    ///
+    /// The arguments are type checked against `inputs` and the returned data is decoded with
+    /// `outputs`.
+    #[allow(clippy::too_many_arguments)]
+    fn call_selector<D: Detokenize, T: Tokenize>(
+        &mut self,
+        from: Address,
+        to: Address,
+        selector: [u8; 4],
+        args: T,
+        value: U256,
+        inputs: &[ParamType],
+        outputs: &[ParamType],
+    ) -> std::result::Result<(D, Self::ReturnReason, u64, Vec<String>), EvmError> {
+        let tokens = args.into_tokens();
+        if tokens.len() != inputs.len() {
+            return Err(eyre::eyre!(
+                "expected {} arguments for selector 0x{}, got {}",
+                inputs.len(),
+                hex::encode(selector),
+                tokens.len()
+            )
+            .into())
         }
+        if let Some(i) =
+            tokens.iter().zip(inputs).position(|(token, param)| !token.type_check(param))
+        {
+            return Err(eyre::eyre!(
+                "argument {} for selector 0x{} is not of type {}",
+                i,
+                hex::encode(selector),
+                inputs[i]
+            )
+            .into())
+        }
+
+        let calldata = [&selector[..], &ethers::abi::encode(&tokens)].concat();
+        let res = self.call_raw(from, to, calldata.into(), value, false)?;
+        let (retdata, status, gas, logs) =
+            check_call_result(self, to, res, None, !outputs.is_empty())?;
+        let tokens = ethers::abi::decode(outputs, retdata.as_ref())
+            .map_err(ethers::contract::AbiError::from)?;
+        let retdata = D::from_tokens(tokens).map_err(ethers::contract::AbiError::from)?;
+        Ok((retdata, status, gas, logs))
     }
 
     fn traces(&self) -> Vec<CallTraceArena> {
@@ -124,7 +282,11 @@ pub trait Evm<State> {
     }
 
     fn reset_traces(&mut self) {}
-    /// Executes the specified EVM call against the state
+    /// Executes the specified EVM call against the state.
+    ///
+    /// Unlike [`call`](Self::call), a revert is not turned into an error, so callers expecting
+    /// one can inspect the returned status and revert data directly. State changes are applied
+    /// the same way as by `call`.
     // TODO: Should we just make this take a `TransactionRequest` or other more
     // ergonomic type?
     #[tracing::instrument(skip_all, fields(from, to, func = %func.name))]
@@ -146,6 +308,11 @@ pub trait Evm<State> {
         self.call_raw(from, to, calldata, value, is_static)
     }
 
+    /// Executes a call with already encoded `calldata` against the state.
+    ///
+    /// This skips the ABI encoding of [`call`](Self::call), which makes it the fast path for
+    /// executing many calls, e.g. when fuzzing. `Bytes` is reference counted, so passing clones
+    /// of the same calldata does not copy it, and the EVM is not re-instantiated between calls.
     fn call_raw(
         &mut self,
         from: Address,
@@ -161,7 +328,59 @@ pub trait Evm<State> {
         from: Address,
         calldata: Bytes,
         value: U256,
-    ) -> Result<(Address, Self::ReturnReason, u64, Vec<String>)>;
+    ) -> Result<(Address, Self::ReturnReason, u64, Vec<String>)> {
+        let DeployResult { address, status, gas_used, logs } =
+            self.try_deploy(from, calldata, value)?;
+        Ok((address, status, gas_used, logs))
+    }
+
+    /// Deploys the contract with the creation `bytecode`, appending the ABI encoded `args` of
+    /// its `constructor`. Contracts without a constructor can be deployed with a `Constructor`
+    /// without inputs and `()` as `args`.
+    fn deploy_with_args<T: Tokenize>(
+        &mut self,
+        from: Address,
+        bytecode: Bytes,
+        constructor: &Constructor,
+        args: T,
+        value: U256,
+    ) -> Result<(Address, Self::ReturnReason, u64, Vec<String>)> {
+        let calldata = constructor
+            .encode_input(bytecode.to_vec(), &args.into_tokens())
+            .wrap_err("could not encode the constructor arguments")?;
+        self.deploy(from, calldata.into(), value)
+    }
+
+    /// Deploys the provided contracts in order, returning their addresses and the gas used by
+    /// each. Every deployment sees the state left by the previous ones, and the first one
+    /// failing aborts the batch with an error naming its index.
+    fn deploy_many(
+        &mut self,
+        from: Address,
+        codes: Vec<Bytes>,
+        value: U256,
+    ) -> Result<Vec<(Address, u64)>> {
+        codes
+            .into_iter()
+            .enumerate()
+            .map(|(i, code)| {
+                let DeployResult { address, gas_used, .. } = self
+                    .try_deploy(from, code, value)
+                    .wrap_err_with(|| format!("deployment {} failed", i))?;
+                Ok((address, gas_used))
+            })
+            .collect()
+    }
+
+    /// Deploys the provided contract bytecode. A deployment which fails, e.g. because the
+    /// constructor reverted, ran out of gas or returned empty or oversized code, is returned as an
+    /// [`EvmError::Execution`] with the decoded revert reason.
+    fn try_deploy(
+        &mut self,
+        from: Address,
+        calldata: Bytes,
+        value: U256,
+    ) -> std::result::Result<DeployResult<Self::ReturnReason>, EvmError>;
 
     /// Runs the `setUp()` function call to instantiate the contract's state
     fn setup(&mut self, address: Address) -> Result<(Self::ReturnReason, Vec<String>)> {
@@ -172,6 +391,29 @@ pub trait Evm<State> {
         Ok((status, logs))
     }
 
+    /// Like [`setup`](Self::setup), but only calls `setUp()` if the contract's `abi` has it
+    /// and reports reverts as [`SetupResult::Reverted`] rather than as an error
+    fn setup_with_abi(&mut self, address: Address, abi: &Abi) -> Result<SetupResult> {
+        if !abi.functions().any(|func| func.name == "setUp" && func.inputs.is_empty()) {
+            return Ok(SetupResult::NotPresent)
+        }
+        let err = match self.setup(address) {
+            Ok((_, logs)) => return Ok(SetupResult::Success { logs }),
+            Err(err) => err,
+        };
+        match err.downcast::<EvmError>() {
+            Ok(EvmError::Execution { reason, logs, .. }) => {
+                Ok(SetupResult::Reverted { reason, logs })
+            }
+            Ok(EvmError::SetupOutOfGas { gas_limit, logs }) => {
+                let reason = EvmError::SetupOutOfGas { gas_limit, logs: Vec::new() }.to_string();
+                Ok(SetupResult::Reverted { reason, logs })
+            }
+            Ok(err) => Err(err.into()),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Runs the `failed()` function call to inspect the test contract's state and
     /// see whether the `failed` state var is set. This is to allow compatibility
     /// with dapptools-style DSTest smart contracts to preserve emitting of logs
@@ -195,12 +437,27 @@ pub trait Evm<State> {
         reason: &Self::ReturnReason,
         should_fail: bool,
     ) -> bool {
+        self.check_success_with(address, reason, should_fail, Self::failed)
+    }
+
+    /// Same as [`check_success`](Self::check_success), but asks `failed` instead of the ds-test
+    /// `failed()` function whether a successful call left the contract in a failed state, for
+    /// test contracts built on other assertion libraries
+    fn check_success_with<F>(
+        &mut self,
+        address: Address,
+        reason: &Self::ReturnReason,
+        should_fail: bool,
+        failed: F,
+    ) -> bool
+    where
+        F: FnOnce(&mut Self, Address) -> Result<bool>,
+    {
         // Check if the call is successful
         let mut success = Self::is_success(reason);
-        // for successful calls, we should also check the ds-test `failed`
-        // value
+        // for successful calls, we should also check whether the contract recorded a failure
         if success {
-            if let Ok(failed) = self.failed(address) {
+            if let Ok(failed) = failed(self, address) {
                 success = !failed;
             }
         }
@@ -226,6 +483,35 @@ pub trait Evm<State> {
     // the EVM is instantiated with a DB that includes any needed contracts?
 }
 
+/// Turns a failed call into an [`EvmError::Execution`] with the decoded revert reason, and a
+/// call to an account without code into an [`EvmError::NoCode`] if return data was expected
+fn check_call_result<S, E: Evm<S> + ?Sized>(
+    evm: &E,
+    to: Address,
+    (retdata, status, gas, logs): (Bytes, E::ReturnReason, u64, Vec<String>),
+    abi: Option<&Abi>,
+    expects_output: bool,
+) -> std::result::Result<(Bytes, E::ReturnReason, u64, Vec<String>), EvmError> {
+    if E::is_fail(&status) {
+        // try to decode the revert reason, else default to the revert status error.
+        let mut reason = foundry_utils::decode_revert(retdata.as_ref(), abi)
+            .unwrap_or_else(|_| E::describe_failure(&status));
+        // solc's panics do not say which operation overflowed
+        if foundry_utils::is_arithmetic_panic(retdata.as_ref()) {
+            if let Some(overflow) = evm.arithmetic_overflow() {
+                reason = format!("{} ({})", reason, overflow);
+            }
+        }
+        return Err(EvmError::Execution { reason, gas_used: gas, logs, revert_data: Some(retdata) })
+    }
+    // calls to accounts without code succeed with no data, which would otherwise surface as a
+    // confusing decoding error
+    if retdata.is_empty() && expects_output && evm.code(to).is_empty() {
+        return Err(EvmError::NoCode { address: to })
+    }
+    Ok((retdata, status, gas, logs))
+}
+
 // Test helpers which are generic over EVM implementation
 #[cfg(test)]
 mod test_helpers {
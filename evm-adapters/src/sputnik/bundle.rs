@@ -0,0 +1,127 @@
+//! All-or-nothing execution of transaction bundles
+use ethers::types::{Address, Bytes, NameOrAddress, TransactionRequest};
+use sputnik::{executor::stack::StackState, ExitReason};
+
+use eyre::Result;
+
+use crate::{
+    sputnik::{Executor, SputnikExecutor},
+    Evm,
+};
+
+/// The outcome of a [`simulate_bundle`](Executor::simulate_bundle) call
+#[derive(Clone, Debug)]
+pub struct BundleResult {
+    /// Whether every transaction succeeded. If not, none of the bundle's state changes were
+    /// kept.
+    pub success: bool,
+    /// The result of each transaction, in bundle order
+    pub results: Vec<(Bytes, ExitReason, u64, Vec<String>)>,
+}
+
+impl<'a, S, E> Executor<S, E>
+where
+    E: SputnikExecutor<S>,
+    S: StackState<'a> + Clone,
+{
+    /// Executes all transactions in order, reverting the state to what it was before the
+    /// bundle if any of them fails, like a Flashbots bundle.
+    ///
+    /// Transactions default to being sent from the zero address with no value. Their gas limit
+    /// defaults to the executor's `gas_limit` and their nonce is validated like with
+    /// [`transact`](Self::transact) if set.
+    pub fn simulate_bundle(&mut self, txs: Vec<TransactionRequest>) -> Result<BundleResult> {
+        let snapshot = self.executor.state().clone();
+        let recorded = self.recording.as_ref().map(Vec::len);
+        let cumulative_gas_used = self.cumulative_gas_used;
+
+        let res = txs.into_iter().map(|tx| self.bundle_transact(tx)).collect::<Result<Vec<_>>>();
+        let success = matches!(
+            &res,
+            Ok(results) if results.iter().all(|(_, status, _, _)| Self::is_success(status))
+        );
+        if !success {
+            // like its state changes, the bundle's transactions are neither recorded nor counted
+            self.reset(snapshot);
+            if let (Some(recording), Some(len)) = (self.recording.as_mut(), recorded) {
+                recording.truncate(len);
+            }
+            self.cumulative_gas_used = cumulative_gas_used;
+        }
+        res.map(|results| BundleResult { success, results })
+    }
+
+    fn bundle_transact(
+        &mut self,
+        tx: TransactionRequest,
+    ) -> Result<(Bytes, ExitReason, u64, Vec<String>)> {
+        let to = match tx.to {
+            Some(NameOrAddress::Address(to)) => to,
+            Some(NameOrAddress::Name(name)) => {
+                eyre::bail!("ENS names are not supported in bundles: {}", name)
+            }
+            None => eyre::bail!("contract creations are not supported in bundles"),
+        };
+        let from = tx.from.unwrap_or_else(Address::zero);
+        let calldata = tx.data.unwrap_or_default();
+        let value = tx.value.unwrap_or_default();
+
+        let gas_limit = tx.gas.map(|gas| gas.as_u64()).unwrap_or(self.gas_limit);
+        let prev_gas_limit = std::mem::replace(&mut self.gas_limit, gas_limit);
+        let res = match tx.nonce {
+            Some(nonce) => {
+                self.transact(from, to, calldata, value, nonce).map_err(eyre::Report::from)
+            }
+            None => self.call_raw(from, to, calldata, value, false),
+        };
+        self.gas_limit = prev_gas_limit;
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sputnik::helpers::vm, test_helpers::COMPILED};
+    use ethers::utils::id;
+    use sputnik::backend::Backend;
+
+    fn greeting<S, E: Evm<S>>(evm: &mut E, addr: Address) -> String {
+        evm.call::<String, _, _>(Address::zero(), addr, "greeting()(string)", (), 0.into(), None)
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn reverts_whole_bundle() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let gm = TransactionRequest::new().to(addr).data(id("gm()").to_vec());
+        // calls a function the contract does not have
+        let nope = TransactionRequest::new().to(addr).data(id("nope()").to_vec());
+
+        evm.start_recording();
+        let gas_used = evm.cumulative_gas_used();
+        let nonce = evm.state().basic(Address::zero()).nonce;
+        let res = evm.simulate_bundle(vec![gm.clone(), nope]).unwrap();
+        assert!(!res.success);
+        assert_eq!(res.results.len(), 2);
+        assert!(matches!(res.results[0].1, ExitReason::Succeed(_)));
+        assert!(matches!(res.results[1].1, ExitReason::Revert(_)));
+        assert_eq!(evm.state().basic(Address::zero()).nonce, nonce);
+        assert_eq!(evm.cumulative_gas_used(), gas_used);
+        assert!(evm.take_recording().is_empty());
+        assert_eq!(greeting(&mut evm, addr), "");
+
+        evm.take_recording();
+        let gas_used = evm.cumulative_gas_used();
+        let res = evm.simulate_bundle(vec![gm]).unwrap();
+        assert!(res.success);
+        assert_eq!(evm.cumulative_gas_used(), gas_used + res.results[0].2);
+        assert_eq!(evm.take_recording().len(), 1);
+        assert_eq!(greeting(&mut evm, addr), "gm");
+    }
+}
@@ -1,6 +1,9 @@
 //! Cheatcode-enabled backend implementation
 use super::Cheatcodes;
-use ethers::types::{H160, H256, U256};
+use ethers::{
+    types::{H160, H256, U256},
+    utils::keccak256,
+};
 use sputnik::backend::{Backend, Basic};
 
 #[derive(Debug, Clone)]
@@ -29,11 +32,25 @@ impl<B: Backend> Backend for CheatcodeBackend<B> {
     }
 
     fn block_hash(&self, number: U256) -> H256 {
-        self.cheats
+        if self.cheats.block_hash_window {
+            let current = self.block_number();
+            if number >= current || current - number > U256::from(256) {
+                return H256::zero()
+            }
+        }
+
+        let hash = self
+            .cheats
             .block_hashes
             .get(&number)
             .cloned()
-            .unwrap_or_else(|| self.backend.block_hash(number))
+            .unwrap_or_else(|| self.backend.block_hash(number));
+        if hash.is_zero() && self.cheats.block_hash_window {
+            let mut buf = [0u8; 32];
+            number.to_big_endian(&mut buf);
+            return keccak256(buf).into()
+        }
+        hash
     }
 
     fn block_number(&self) -> U256 {
@@ -61,7 +78,7 @@ impl<B: Backend> Backend for CheatcodeBackend<B> {
     }
 
     fn chain_id(&self) -> U256 {
-        self.backend.chain_id()
+        self.cheats.chain_id.unwrap_or_else(|| self.backend.chain_id())
     }
 
     fn exists(&self, address: H160) -> bool {
@@ -1,12 +1,13 @@
 //! Hooks to EVM execution
 use super::{
-    backend::CheatcodeBackend, memory_stackstate_owned::MemoryStackStateOwned, ConsoleCalls,
-    HEVMCalls, HevmConsoleEvents,
+    backend::CheatcodeBackend,
+    memory_stackstate_owned::{CallEndHook, MemoryStackStateOwned},
+    ConsoleCalls, HEVMCalls, HevmConsoleEvents,
 };
 use crate::{
     call_tracing::{CallTrace, CallTraceArena, LogCallOrder},
     sputnik::{cheatcodes::memory_stackstate_owned::ExpectedEmit, Executor, SputnikExecutor},
-    Evm, ASSUME_MAGIC_RETURN_CODE,
+    Evm, EvmError, ASSUME_MAGIC_RETURN_CODE,
 };
 use std::collections::BTreeMap;
 
@@ -16,33 +17,38 @@ use serde::Deserialize;
 use sputnik::{
     backend::Backend,
     executor::stack::{
-        Log, PrecompileFailure, PrecompileOutput, PrecompileSet, StackExecutor, StackExitKind,
-        StackState, StackSubstateMetadata,
+        Log, MemoryStackSubstate, PrecompileFailure, PrecompileOutput, PrecompileSet,
+        StackExecutor, StackExitKind, StackState, StackSubstateMetadata,
     },
-    gasometer, Capture, Config, Context, CreateScheme, ExitError, ExitReason, ExitRevert,
-    ExitSucceed, Handler, Memory, Opcode, Runtime, Transfer,
+    gasometer, Capture, Config, Context, CreateScheme, ExitError, ExitFatal, ExitReason,
+    ExitRevert, ExitSucceed, Handler, Memory, Opcode, Runtime, Transfer,
 };
 use std::{process::Command, rc::Rc};
 
 use ethers::{
     abi::{RawLog, Token},
-    contract::EthLogDecode,
+    contract::{encode_function_data, EthLogDecode},
     core::{abi::AbiDecode, k256::ecdsa::SigningKey, utils},
     signers::{LocalWallet, Signer},
     solc::{artifacts::CompactContractBytecode, ProjectPathsConfig},
-    types::{Address, H160, H256, U256},
+    types::{Address, H160, H256, I256, U256},
 };
 
 use std::{convert::Infallible, str::FromStr};
 
 use crate::sputnik::cheatcodes::{
     debugger::{CheatOp, DebugArena, DebugNode, DebugStep, OpCode},
-    memory_stackstate_owned::Prank,
+    memory_stackstate_owned::{
+        BalanceChange, BalanceChangeReason, BreakpointInfo, CallKind, GasBreakdown, Prank,
+        RecordedCall, RecursionDepth, StorageAccesses,
+    },
+    overflow::OverflowTracker,
     patch_hardhat_console_log_selector,
 };
 use once_cell::sync::Lazy;
 
-use ethers::abi::Tokenize;
+use ethers::abi::{Abi, Detokenize, Tokenize};
+use foundry_utils::IntoFunction;
 
 // This is now getting us the right hash? Also tried [..20]
 // Lazy::new(|| Address::from_slice(&keccak256("hevm cheat code")[12..]));
@@ -105,6 +111,27 @@ pub struct CheatcodeHandler<H> {
     handler: H,
     enable_ffi: bool,
     console_logs: Vec<String>,
+    /// `console.log` messages above this level are discarded, see [`console_log_level`]
+    log_verbosity: u8,
+}
+
+/// The verbosity a `console.log` message requires to be kept. Messages prefixed with
+/// `[debug]` have level 3, messages prefixed with `[trace]` level 4, and all other messages
+/// (including `[info]`) level 2, matching the verbosity at which `forge test` prints logs.
+pub fn console_log_level(message: &str) -> u8 {
+    if message.starts_with("[trace]") {
+        4
+    } else if message.starts_with("[debug]") {
+        3
+    } else {
+        2
+    }
+}
+
+/// Decodes the ds-test logging events among `logs`, e.g. `log_named_uint(string,uint256)`, in
+/// order. All other logs are skipped.
+pub fn decode_ds_test_logs(logs: &[RawLog]) -> Vec<HevmConsoleEvents> {
+    logs.iter().filter_map(|log| HevmConsoleEvents::decode_log(log).ok()).collect()
 }
 
 pub(crate) fn convert_log(log: Log) -> Option<String> {
@@ -199,6 +226,15 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> SputnikExecutor<CheatcodeStackState<'
         self.handler.state().all_logs.clone()
     }
 
+    fn arithmetic_overflow(&self) -> Option<String> {
+        self.handler.state().arithmetic_overflow.map(|overflow| overflow.to_string())
+    }
+
+    fn step_limit_exceeded(&self) -> Option<usize> {
+        let state = self.handler.state();
+        state.max_steps.filter(|max| state.step_count > *max)
+    }
+
     fn transact_call(
         &mut self,
         caller: H160,
@@ -210,6 +246,10 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> SputnikExecutor<CheatcodeStackState<'
     ) -> (ExitReason, Vec<u8>) {
         // reset all_logs because its a new call
         self.state_mut().all_logs = vec![];
+        self.state_mut().arithmetic_overflow = None;
+        self.state_mut().debug_step_count = 0;
+        self.state_mut().step_count = 0;
+        let expected_call_revert = self.state_mut().expected_call_revert.take();
 
         let transaction_cost = gasometer::call_transaction_cost(&data, &access_list);
         match self.state_mut().metadata_mut().gasometer_mut().record_transaction(transaction_cost) {
@@ -266,7 +306,11 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> SputnikExecutor<CheatcodeStackState<'
                         )]),
                     )
                 }
-                (s, v)
+
+                match expected_call_revert {
+                    Some(expected) => check_call_revert(s, v, expected),
+                    None => (s, v),
+                }
             }
             Capture::Trap(_) => {
                 self.state_mut().increment_call_index();
@@ -282,36 +326,23 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> SputnikExecutor<CheatcodeStackState<'
         init_code: Vec<u8>,
         gas_limit: u64,
         access_list: Vec<(H160, Vec<H256>)>,
-    ) -> ExitReason {
-        // reset all_logs because its a new call
-        self.state_mut().all_logs = vec![];
-
-        let transaction_cost = gasometer::create_transaction_cost(&init_code, &access_list);
-        match self.state_mut().metadata_mut().gasometer_mut().record_transaction(transaction_cost) {
-            Ok(()) => (),
-            Err(e) => return e.into(),
-        };
-        self.handler.initialize_with_access_list(access_list);
+    ) -> (ExitReason, Vec<u8>) {
+        let scheme = CreateScheme::Legacy { caller };
+        self.transact_create_with_scheme(caller, value, init_code, scheme, gas_limit, access_list)
+    }
 
-        match self.create_inner(
-            caller,
-            CreateScheme::Legacy { caller },
-            value,
-            init_code,
-            Some(gas_limit),
-            false,
-        ) {
-            Capture::Exit((s, _, _)) => {
-                if self.state().trace_enabled {
-                    self.state_mut().increment_call_index();
-                }
-                s
-            }
-            Capture::Trap(_) => {
-                self.state_mut().increment_call_index();
-                unreachable!()
-            }
-        }
+    fn transact_create2(
+        &mut self,
+        caller: H160,
+        value: U256,
+        init_code: Vec<u8>,
+        salt: H256,
+        gas_limit: u64,
+        access_list: Vec<(H160, Vec<H256>)>,
+    ) -> (ExitReason, Vec<u8>) {
+        let code_hash = H256::from(utils::keccak256(&init_code));
+        let scheme = CreateScheme::Create2 { caller, code_hash, salt };
+        self.transact_create_with_scheme(caller, value, init_code, scheme, gas_limit, access_list)
     }
 
     fn create_address(&self, scheme: CreateScheme) -> Address {
@@ -328,6 +359,22 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> SputnikExecutor<CheatcodeStackState<'
         logs.into_iter().map(|log| RawLog { topics: log.topics, data: log.data }).collect()
     }
 
+    fn emitted_logs(&self) -> Vec<ethers::types::Log> {
+        // the logs of reverted frames are discarded with their substate, so the remaining logs
+        // are in emission order
+        let logs = self.state().substate.logs().to_vec();
+        logs.into_iter()
+            .enumerate()
+            .map(|(idx, log)| ethers::types::Log {
+                address: log.address,
+                topics: log.topics,
+                data: log.data.into(),
+                transaction_log_index: Some(idx.into()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
     fn traces(&self) -> Vec<CallTraceArena> {
         self.state().traces.clone()
     }
@@ -373,7 +420,12 @@ impl<'a, 'b, B: Backend, P: PrecompileSet>
 
         // create the executor and wrap it with the cheatcode handler
         let executor = StackExecutor::new_with_precompiles(state, config, precompiles);
-        let executor = CheatcodeHandler { handler: executor, enable_ffi, console_logs: Vec::new() };
+        let executor = CheatcodeHandler {
+            handler: executor,
+            enable_ffi,
+            console_logs: Vec::new(),
+            log_verbosity: u8::MAX,
+        };
 
         let mut evm = Executor::from_executor(executor, gas_limit);
 
@@ -386,6 +438,550 @@ impl<'a, 'b, B: Backend, P: PrecompileSet>
 
         evm
     }
+
+    /// Replaces the backend, e.g. with a fork at another block, discarding all changes made
+    /// so far along with all snapshots. The overrides of the block environment and of
+    /// `tx.origin` are kept, `block_number` overrides the block number if set.
+    ///
+    /// This is cheaper than creating a new executor, since all other settings are kept as well.
+    pub fn reset_backend(&mut self, backend: B, block_number: Option<U256>) {
+        let config = self.executor.handler.config();
+        let metadata = StackSubstateMetadata::new(self.gas_limit, config);
+        let state = self.executor.state_mut();
+        state.backend.backend = backend;
+        state.backend.cheats.accounts.clear();
+        state.backend.cheats.block_hashes.clear();
+        if block_number.is_some() {
+            state.backend.cheats.block_number = block_number;
+        }
+        state.substate = MemoryStackSubstate::new(metadata);
+        self.clear_snapshots();
+
+        self.initialize_contracts([
+            (*CHEATCODE_ADDRESS, vec![0u8; 1].into()),
+            (*CONSOLE_ADDRESS, vec![0u8; 1].into()),
+        ]);
+    }
+
+    /// Replaces the precompile at `address` with `f`, which maps the call's input to its
+    /// output. Stubbed precompiles always succeed and consume no gas.
+    pub fn stub_precompile(&mut self, address: Address, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.executor.state_mut().precompile_stubs.insert(address, Rc::new(f));
+    }
+
+    /// Stubs the `ecrecover` precompile (0x01)
+    pub fn stub_ecrecover(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(1), f)
+    }
+
+    /// Stubs the `sha256` precompile (0x02)
+    pub fn stub_sha256(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(2), f)
+    }
+
+    /// Stubs the `ripemd160` precompile (0x03)
+    pub fn stub_ripemd160(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(3), f)
+    }
+
+    /// Stubs the `identity` precompile (0x04)
+    pub fn stub_identity(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(4), f)
+    }
+
+    /// Stubs the `modexp` precompile (0x05)
+    pub fn stub_modexp(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(5), f)
+    }
+
+    /// Stubs the `ecAdd` precompile (0x06)
+    pub fn stub_ec_add(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(6), f)
+    }
+
+    /// Stubs the `ecMul` precompile (0x07)
+    pub fn stub_ec_mul(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(7), f)
+    }
+
+    /// Stubs the `ecPairing` precompile (0x08)
+    pub fn stub_ec_pairing(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(8), f)
+    }
+
+    /// Stubs the `blake2f` precompile (0x09)
+    pub fn stub_blake2f(&mut self, f: impl Fn(&[u8]) -> Vec<u8> + 'static) {
+        self.stub_precompile(H160::from_low_u64_be(9), f)
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw), recording every external call made during
+    /// execution (excluding the top-level call and calls to the cheatcode and console
+    /// addresses) in chronological order
+    pub fn call_raw_with_calldata_log(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        is_static: bool,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), Vec<RecordedCall>)>
+    {
+        self.executor.state_mut().calldata_log = Some(Vec::new());
+        let res = self.call_raw(from, to, calldata, value, is_static);
+        let calldata_log = self.executor.state_mut().calldata_log.take().unwrap_or_default();
+        Ok((res?, calldata_log))
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw), recording every balance change in chronological
+    /// order: value sent with calls (including the top-level call) and creations, balances sent
+    /// to the beneficiaries of `selfdestruct`s and finally the sender's gas payment and the
+    /// coinbase's tip. Changes made in frames which were later reverted are still included.
+    ///
+    /// The executor does not charge for gas, so the gas payment and tip are derived from the gas
+    /// used by the transaction and the backend's gas price and base fee, and are only logged. The
+    /// base fee is burnt, so only the part of the gas price above it is credited to the coinbase.
+    pub fn call_raw_with_balance_log(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        is_static: bool,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), Vec<BalanceChange>)>
+    {
+        let intrinsic_gas = foundry_utils::intrinsic_gas(calldata.as_ref());
+        self.executor.state_mut().balance_log = Some(Vec::new());
+        let res = self.call_raw(from, to, calldata, value, is_static);
+        let mut balance_log = self.executor.state_mut().balance_log.take().unwrap_or_default();
+        let res = res?;
+
+        let state = self.executor.state();
+        let gas = U256::from(res.2 + intrinsic_gas);
+        let gas_price = state.gas_price();
+        let payment = gas * gas_price;
+        let tip = gas * gas_price.saturating_sub(state.block_base_fee_per_gas());
+        if !payment.is_zero() {
+            let delta = -I256::from_raw(payment);
+            balance_log.push(BalanceChange {
+                address: from,
+                delta,
+                reason: BalanceChangeReason::Gas,
+            });
+        }
+        if !tip.is_zero() {
+            balance_log.push(BalanceChange {
+                address: state.block_coinbase(),
+                delta: I256::from_raw(tip),
+                reason: BalanceChangeReason::CoinbaseTip,
+            });
+        }
+        Ok((res, balance_log))
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw) with tracing enabled, returning the tree of all
+    /// calls and creations made, whether tracing is enabled on the executor or not
+    pub fn call_raw_with_trace(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        is_static: bool,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), CallTraceArena)> {
+        let prev = self.set_tracing_enabled(true);
+        let index = self.executor.state().call_index;
+        let res = self.call_raw(from, to, calldata, value, is_static);
+        self.set_tracing_enabled(prev);
+        let trace = self.executor.state().traces[index].clone();
+        Ok((res?, trace))
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw) with debugging enabled, returning the debug steps
+    /// of all frames. At most `max_steps` steps are recorded if set, later steps are still
+    /// executed.
+    pub fn call_raw_with_debug_arena(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        max_steps: Option<usize>,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), DebugArena)> {
+        let state = self.executor.state_mut();
+        let prev_debug = std::mem::replace(&mut state.debug_enabled, true);
+        let prev_max_steps = std::mem::replace(&mut state.max_debug_steps, max_steps);
+        let index = state.call_index;
+        state.debug_steps[index] = Default::default();
+
+        let res = self.call_raw(from, to, calldata, value, false);
+
+        let state = self.executor.state_mut();
+        state.debug_enabled = prev_debug;
+        state.max_debug_steps = prev_max_steps;
+        Ok((res?, state.debug_steps[index].clone()))
+    }
+
+    /// Like [`call_raw_with_debug_arena`](Self::call_raw_with_debug_arena), but returns every
+    /// executed step in the order of [`DebugArena::flatten`]
+    pub fn call_raw_with_debug_steps(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        max_steps: Option<usize>,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), Vec<DebugStep>)> {
+        let (res, arena) = self.call_raw_with_debug_arena(from, to, calldata, value, max_steps)?;
+        let mut flattened = Vec::new();
+        arena.flatten(0, &mut flattened);
+        let steps = flattened.into_iter().flat_map(|(_, steps, _)| steps).collect();
+        Ok((res, steps))
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw), recording the revert data of every frame which
+    /// reverted, innermost first. This shows how a revert propagated, e.g. that a failing
+    /// `require` was caught and rethrown with a different message.
+    ///
+    /// Reverts which were caught by a frame which then succeeded are included as well.
+    pub fn call_raw_with_revert_chain(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        is_static: bool,
+    ) -> eyre::Result<(
+        (ethers::types::Bytes, ExitReason, u64, Vec<String>),
+        Vec<(Address, ethers::types::Bytes)>,
+    )> {
+        self.executor.state_mut().revert_chain = Some(Vec::new());
+        let res = self.call_raw(from, to, calldata, value, is_static);
+        let revert_chain = self.executor.state_mut().revert_chain.take().unwrap_or_default();
+        let revert_chain = revert_chain.into_iter().map(|(addr, data)| (addr, data.into()));
+        Ok((res?, revert_chain.collect()))
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw), splitting the reported gas into the gas spent on
+    /// memory expansion and on everything else. Asserting on the execution gas alone keeps gas
+    /// snapshots stable across changes which only shift the memory layout.
+    pub fn call_raw_with_gas_breakdown(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        is_static: bool,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), GasBreakdown)> {
+        let intrinsic = foundry_utils::intrinsic_gas(&calldata);
+        self.executor.state_mut().memory_gas = Some(0);
+        let res = self.call_raw(from, to, calldata, value, is_static);
+        let memory = self.executor.state_mut().memory_gas.take().unwrap_or_default();
+        let res = res?;
+        let breakdown = GasBreakdown { execution: res.2.saturating_sub(memory), memory, intrinsic };
+        Ok((res, breakdown))
+    }
+
+    /// Calls the parameterless function `func` of `to`, which is expected to recurse until it
+    /// fails, and reports how deep the recursion went before running out of gas or hitting the
+    /// call depth limit. The outcome of the call itself is ignored.
+    pub fn measure_recursion_depth<F: IntoFunction>(
+        &mut self,
+        from: Address,
+        to: Address,
+        func: F,
+    ) -> eyre::Result<RecursionDepth> {
+        let calldata = encode_function_data(&func.into(), ())?;
+        self.executor.state_mut().call_depth = Some(Default::default());
+        let res = self.call_raw(from, to, calldata, 0.into(), false);
+        let call_depth = self.executor.state_mut().call_depth.take().unwrap_or_default();
+        res?;
+        Ok(call_depth)
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw), aborting delegatecalls to code which is already
+    /// executing in the same storage context, e.g. a proxy and an implementation which delegate
+    /// to each other. Instead of running out of gas, the delegatecall reverts with
+    /// `delegatecall cycle detected: A → B → A` and the cycle's code addresses are returned.
+    pub fn call_raw_detect_delegate_cycles(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        is_static: bool,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), Option<Vec<Address>>)>
+    {
+        let state = self.executor.state_mut();
+        state.call_frames = Some(vec![(to, to)]);
+        state.delegate_cycle = None;
+        let res = self.call_raw(from, to, calldata, value, is_static);
+        let state = self.executor.state_mut();
+        state.call_frames = None;
+        Ok((res?, state.delegate_cycle.take()))
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw), returning all storage slots it read or wrote
+    ///
+    /// This does not interfere with a recording started by the `record` cheatcode.
+    pub fn call_raw_with_storage_accesses(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        is_static: bool,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), StorageAccesses)> {
+        let prev_accesses = self.executor.state_mut().accesses.replace(Default::default());
+        let res = self.call_raw(from, to, calldata, value, is_static);
+        let state = self.executor.state_mut();
+        let accesses = std::mem::replace(&mut state.accesses, prev_accesses).unwrap_or_default();
+        Ok((res?, accesses.into()))
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw) with `tx.origin` set to `origin` instead of the
+    /// backend's origin, e.g. to call a contract through another one and check that the
+    /// contract distinguishes `tx.origin` from `msg.sender`. The origin is restored afterwards.
+    pub fn call_raw_with_origin(
+        &mut self,
+        from: Address,
+        origin: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+    ) -> eyre::Result<(ethers::types::Bytes, ExitReason, u64, Vec<String>)> {
+        let cheats = &mut self.executor.state_mut().backend.cheats;
+        let prev_origin = cheats.origin.replace(origin);
+        let res = self.call_raw(from, to, calldata, value, false);
+        self.executor.state_mut().backend.cheats.origin = prev_origin;
+        res
+    }
+
+    /// Performs a [`call`](Evm::call) as if the chain had id `chain_id`, e.g. to check that
+    /// EIP-712 signatures are not valid across chains. The executor's chain id is restored
+    /// afterwards.
+    #[allow(clippy::too_many_arguments)]
+    pub fn call_with_chain_id<D: Detokenize, T: Tokenize, F: IntoFunction>(
+        &mut self,
+        from: Address,
+        to: Address,
+        func: F,
+        args: T,
+        value: U256,
+        abi: Option<&Abi>,
+        chain_id: u64,
+    ) -> Result<(D, ExitReason, u64, Vec<String>), EvmError> {
+        let cheats = &mut self.executor.state_mut().backend.cheats;
+        let prev_chain_id = cheats.chain_id.replace(chain_id.into());
+        let res = self.call(from, to, func, args, value, abi);
+        self.executor.state_mut().backend.cheats.chain_id = prev_chain_id;
+        res
+    }
+
+    /// Discards `console.log` messages whose [`console_log_level`] is above `level`. All
+    /// messages are kept by default.
+    pub fn set_log_verbosity(&mut self, level: u8) {
+        self.executor.log_verbosity = level;
+    }
+
+    /// Enables or disables balance checks, see
+    /// [`balance_checks`](MemoryStackStateOwned::balance_checks). Disabling them allows sending
+    /// value from accounts which were never funded, e.g. impersonated accounts on a fork.
+    pub fn set_balance_checks(&mut self, enabled: bool) {
+        self.executor.state_mut().balance_checks = enabled;
+    }
+
+    /// Enables or disables explaining arithmetic over/underflow panics with the operands of
+    /// the failing operation, see [`overflow_tracing`](MemoryStackStateOwned::overflow_tracing)
+    pub fn set_overflow_tracing(&mut self, enabled: bool) {
+        self.executor.state_mut().overflow_tracing = enabled;
+    }
+
+    /// Sets the maximum number of opcodes executed per transaction, see
+    /// [`max_steps`](MemoryStackStateOwned::max_steps). Calls and deployments which exceed it
+    /// fail with [`EvmError::StepLimitExceeded`](crate::EvmError::StepLimitExceeded).
+    pub fn set_step_limit(&mut self, max_steps: Option<usize>) {
+        self.executor.state_mut().max_steps = max_steps;
+    }
+
+    /// Makes `BLOCKHASH` return `hash` for the block `number` instead of the backend's hash,
+    /// e.g. to deterministically test commit-reveal schemes. Hashes set with the `roll`
+    /// cheatcode are replaced.
+    pub fn set_block_hash(&mut self, number: U256, hash: H256) {
+        self.executor.state_mut().backend.cheats.block_hashes.insert(number, hash);
+    }
+
+    /// Makes calls to `to` return `return_data` without executing its code if their calldata
+    /// is `calldata` or starts with it, like the `mockCall` cheatcode. Mocking just a selector
+    /// thus mocks all calls of the function, with calldata matching exactly taking precedence.
+    ///
+    /// Only calls made by contracts are mocked, not the call made by [`call_raw`](Evm::call_raw)
+    /// itself.
+    pub fn mock_call(
+        &mut self,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        return_data: ethers::types::Bytes,
+    ) {
+        self.executor
+            .state_mut()
+            .mocked_calls
+            .entry(to)
+            .or_default()
+            .insert(calldata.to_vec(), return_data.to_vec());
+    }
+
+    /// Removes all mocks set by [`mock_call`](Self::mock_call) or the `mockCall` cheatcode
+    pub fn clear_mocked_calls(&mut self) {
+        self.executor.state_mut().mocked_calls.clear();
+    }
+
+    /// Lets `f` replace the outcome of every call made by a contract during execution
+    /// (excluding calls to the cheatcode and console addresses and mocked calls), e.g. to make
+    /// all calls to an address return no data. `f` receives the code address, the input, and the
+    /// status and return data of the finished call, and returns the status and return data the
+    /// caller sees instead.
+    ///
+    /// Only the outcome is replaced: a successful call's state changes are kept even if `f`
+    /// turns it into a revert, the changes of a reverted call are not restored if `f`
+    /// turns it into a success, and the gas used does not change. Results which are not
+    /// consistent with the state can thus make contracts behave in ways that are impossible
+    /// on chain.
+    pub fn set_call_end_hook(
+        &mut self,
+        f: impl Fn(Address, &[u8], ExitReason, Vec<u8>) -> (ExitReason, Vec<u8>) + 'static,
+    ) {
+        self.executor.state_mut().call_end_hook = Some(Rc::new(f));
+    }
+
+    /// Like [`set_call_end_hook`](Self::set_call_end_hook), but runs `f` after the hooks set so
+    /// far instead of replacing them. Each hook receives the outcome returned by the previous
+    /// one, so e.g. a revert requested by an earlier hook is what later hooks see.
+    pub fn add_call_end_hook(
+        &mut self,
+        f: impl Fn(Address, &[u8], ExitReason, Vec<u8>) -> (ExitReason, Vec<u8>) + 'static,
+    ) {
+        let state = self.executor.state_mut();
+        let hook: CallEndHook = match state.call_end_hook.take() {
+            Some(prev) => Rc::new(move |address, input: &[u8], status, retdata| {
+                let (status, retdata) = prev(address, input, status, retdata);
+                f(address, input, status, retdata)
+            }),
+            None => Rc::new(f),
+        };
+        state.call_end_hook = Some(hook);
+    }
+
+    /// Removes the hooks set by [`set_call_end_hook`](Self::set_call_end_hook) and
+    /// [`add_call_end_hook`](Self::add_call_end_hook)
+    pub fn clear_call_end_hook(&mut self) {
+        self.executor.state_mut().call_end_hook = None;
+    }
+
+    /// Expects the next [`call_raw`](Evm::call_raw) to revert, with exactly the revert data
+    /// `expected` if set or with any data otherwise, like the `expectRevert` cheatcode does for
+    /// the next call of a test contract.
+    ///
+    /// If the expectation is met, the call is reported as successful and returns the revert
+    /// data. Otherwise it reverts with a description of the mismatch. Only the outcome of the
+    /// call itself is checked, so reverts of subcalls which are caught do not satisfy it.
+    pub fn expect_revert(&mut self, expected: Option<ethers::types::Bytes>) {
+        self.executor.state_mut().expected_call_revert = Some(expected.map(|data| data.to_vec()));
+    }
+
+    /// Removes all precompile stubs, so that calls pass through to the real precompiles again
+    pub fn clear_precompile_stubs(&mut self) {
+        self.executor.state_mut().precompile_stubs.clear();
+    }
+}
+
+/// Clones the executor along with its whole state, e.g. to run tests in parallel from the state
+/// after `setUp()`. The backend and all accounts touched so far are duplicated, which is
+/// expensive for large states, e.g. with a warm fork cache.
+impl<'a, 'b, B: Backend + Clone, P: PrecompileSet> Clone
+    for Executor<CheatcodeStackState<'a, B>, CheatcodeStackExecutor<'a, 'b, B, P>>
+{
+    fn clone(&self) -> Self {
+        let handler = StackExecutor::new_with_precompiles(
+            self.executor.state().clone(),
+            self.executor.handler.config(),
+            self.executor.handler.precompiles(),
+        );
+        let executor = CheatcodeHandler {
+            handler,
+            enable_ffi: self.executor.enable_ffi,
+            console_logs: self.executor.console_logs.clone(),
+            log_verbosity: self.executor.log_verbosity,
+        };
+        self.clone_with(executor)
+    }
+}
+
+impl<'a, 'b, B: Backend + Clone, P: PrecompileSet>
+    Executor<CheatcodeStackState<'a, B>, CheatcodeStackExecutor<'a, 'b, B, P>>
+{
+    /// Executes a [`call_raw`](Evm::call_raw) on a [clone](Clone) of the executor, returning
+    /// the clone along with the call's result. The clone holds the post-state of the call and
+    /// can be queried like any executor, while `self` is left untouched.
+    pub fn call_raw_uncommitted(
+        &self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+    ) -> eyre::Result<((ethers::types::Bytes, ExitReason, u64, Vec<String>), Self)> {
+        let mut post_state = self.clone();
+        let res = post_state.call_raw(from, to, calldata, value, false)?;
+        Ok((res, post_state))
+    }
+
+    /// Like [`call_raw_uncommitted`](Self::call_raw_uncommitted), but for a
+    /// [`deploy`](Evm::deploy), e.g. to check the size and gas of a deployment without
+    /// changing the state. The deployed code can be read from the returned post-state.
+    pub fn deploy_uncommitted(
+        &self,
+        from: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+    ) -> eyre::Result<((Address, ExitReason, u64, Vec<String>), Self)> {
+        let mut post_state = self.clone();
+        let res = post_state.deploy(from, calldata, value)?;
+        Ok((res, post_state))
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw) once under each of the EVM configurations, e.g.
+    /// `Config::berlin()` and `Config::london()`, returning the results in the same order.
+    ///
+    /// Every call runs on a [clone](Clone) of the executor with only the configuration swapped,
+    /// so the executor's own state is not modified. This is meant for checking hardfork dependent
+    /// behavior, like the availability of opcodes.
+    pub fn call_raw_across_configs(
+        &self,
+        from: Address,
+        to: Address,
+        calldata: ethers::types::Bytes,
+        value: U256,
+        configs: &[&'a Config],
+    ) -> eyre::Result<Vec<(ethers::types::Bytes, ExitReason, u64, Vec<String>)>> {
+        configs
+            .iter()
+            .map(|config| {
+                let mut state = self.executor.state().clone();
+                *state.metadata_mut() = StackSubstateMetadata::new(self.gas_limit, config);
+                let handler = StackExecutor::new_with_precompiles(
+                    state,
+                    config,
+                    self.executor.handler.precompiles(),
+                );
+                let executor = CheatcodeHandler {
+                    handler,
+                    enable_ffi: self.executor.enable_ffi,
+                    console_logs: self.executor.console_logs.clone(),
+                    log_verbosity: self.executor.log_verbosity,
+                };
+                self.clone_with(executor).call_raw(from, to, calldata.clone(), value, false)
+            })
+            .collect()
+    }
 }
 
 // helper for creating an exit type
@@ -461,7 +1057,67 @@ fn revert_return_evm<T: ToString>(
     }
 }
 
+// checks the outcome of a top level call against the revert set by `Executor::expect_revert`,
+// turning a matching revert into a success and anything else into a revert with the reason
+fn check_call_revert(
+    status: ExitReason,
+    retdata: Vec<u8>,
+    expected: Option<Vec<u8>>,
+) -> (ExitReason, Vec<u8>) {
+    let reason = match (&status, expected) {
+        (ExitReason::Revert(_), None) => {
+            return (ExitReason::Succeed(ExitSucceed::Returned), retdata)
+        }
+        (ExitReason::Revert(_), Some(expected)) if expected == retdata => {
+            return (ExitReason::Succeed(ExitSucceed::Returned), retdata)
+        }
+        (ExitReason::Revert(_), Some(expected)) => format!(
+            "Error data != expected error data: 0x{} != 0x{}",
+            hex::encode(&retdata),
+            hex::encode(&expected)
+        ),
+        _ => "Expected revert did not revert".to_string(),
+    };
+    (ExitReason::Revert(ExitRevert::Reverted), ethers::abi::encode(&[Token::String(reason)]))
+}
+
 impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P> {
+    fn transact_create_with_scheme(
+        &mut self,
+        caller: H160,
+        value: U256,
+        init_code: Vec<u8>,
+        scheme: CreateScheme,
+        gas_limit: u64,
+        access_list: Vec<(H160, Vec<H256>)>,
+    ) -> (ExitReason, Vec<u8>) {
+        // reset all_logs because its a new call
+        self.state_mut().all_logs = vec![];
+        self.state_mut().arithmetic_overflow = None;
+        self.state_mut().debug_step_count = 0;
+        self.state_mut().step_count = 0;
+
+        let transaction_cost = gasometer::create_transaction_cost(&init_code, &access_list);
+        match self.state_mut().metadata_mut().gasometer_mut().record_transaction(transaction_cost) {
+            Ok(()) => (),
+            Err(e) => return (e.into(), Vec::new()),
+        };
+        self.handler.initialize_with_access_list(access_list);
+
+        match self.create_inner(caller, scheme, value, init_code, Some(gas_limit), false) {
+            Capture::Exit((s, _, v)) => {
+                if self.state().trace_enabled {
+                    self.state_mut().increment_call_index();
+                }
+                (s, v)
+            }
+            Capture::Trap(_) => {
+                self.state_mut().increment_call_index();
+                unreachable!()
+            }
+        }
+    }
+
     /// Checks whether the provided call reverted with an expected revert reason.
     fn expected_revert(
         &mut self,
@@ -533,10 +1189,58 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
             Ok(inner) => inner,
             Err(err) => return evm_error(&err.to_string()),
         };
-        self.console_logs.push(decoded.to_string());
+        let message = decoded.to_string();
+        if console_log_level(&message) <= self.log_verbosity {
+            self.console_logs.push(message);
+        }
         Capture::Exit((ExitReason::Succeed(ExitSucceed::Stopped), vec![]))
     }
 
+    /// Updates the deepest frame entered if call depths are tracked. `None` means that a frame
+    /// could not be entered because of the call depth limit.
+    fn record_call_depth(&mut self, depth: Option<usize>) {
+        if let Some(call_depth) = self.state_mut().call_depth.as_mut() {
+            match depth {
+                Some(depth) => call_depth.depth = call_depth.depth.max(depth),
+                None => call_depth.hit_call_depth_limit = true,
+            }
+        }
+    }
+
+    /// Adds the memory expansion cost of a finished frame if memory gas is tracked
+    fn record_memory_gas(&mut self, memory_len: U256) {
+        if let Some(memory_gas) = self.state_mut().memory_gas.as_mut() {
+            // the cost only depends on the final size of the memory
+            let words = (memory_len / 32).as_u64();
+            *memory_gas += 3 * words + words * words / 512;
+        }
+    }
+
+    /// Appends a reverted frame to the revert chain if it is recorded
+    fn record_revert(&mut self, address: H160, revert_data: Vec<u8>) {
+        if let Some(revert_chain) = self.state_mut().revert_chain.as_mut() {
+            revert_chain.push((address, revert_data));
+        }
+    }
+
+    /// Records that `value` moved from `source` to `target` if balance changes are logged
+    fn record_balance_change(
+        &mut self,
+        source: H160,
+        target: H160,
+        value: U256,
+        reason: BalanceChangeReason,
+    ) {
+        if value.is_zero() {
+            return
+        }
+        if let Some(balance_log) = self.state_mut().balance_log.as_mut() {
+            let delta = I256::from_raw(value);
+            balance_log.push(BalanceChange { address: source, delta: -delta, reason });
+            balance_log.push(BalanceChange { address: target, delta, reason });
+        }
+    }
+
     /// Adds CheatOp to the latest DebugArena
     fn add_debug(&mut self, cheatop: CheatOp) {
         if self.state().debug_enabled {
@@ -910,6 +1614,16 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
 
                 self.state_mut().labels.insert(address, label);
             }
+            HEVMCalls::Breakpoint(inner) => {
+                self.add_debug(CheatOp::BREAKPOINT);
+                let info = BreakpointInfo {
+                    caller: msg_sender,
+                    pc: self.state().current_pc,
+                    gas_left: self.state().metadata().gasometer().gas(),
+                    depth: self.state().metadata().depth().unwrap_or_default(),
+                };
+                self.state_mut().breakpoints.push((inner.0, info));
+            }
             HEVMCalls::Assume(inner) => {
                 self.add_debug(CheatOp::ASSUME);
                 if !inner.0 {
@@ -932,21 +1646,80 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
     // NB: This function is copy-pasted from upstream's `execute`, adjusted so that we call the
     // Runtime with our own handler
     pub fn execute(&mut self, runtime: &mut Runtime) -> ExitReason {
+        if self.state().max_steps.is_some() {
+            return self.limited_execute(runtime)
+        }
         match runtime.run(self) {
             Capture::Exit(s) => s,
             Capture::Trap(_) => unreachable!("Trap is Infallible"),
         }
     }
 
-    /// Executes the call/create while also tracking the state of the machine (including opcodes)
-    fn debug_execute(
-        &mut self,
-        runtime: &mut Runtime,
-        address: Address,
-        code: Rc<Vec<u8>>,
-        creation: bool,
-    ) -> ExitReason {
-        let depth = if let Some(depth) = self.state().metadata().depth() { depth + 1 } else { 0 };
+    /// Executes the call/create step by step, counting the steps towards `max_steps`
+    fn limited_execute(&mut self, runtime: &mut Runtime) -> ExitReason {
+        loop {
+            if let Err(reason) = self.limited_step(runtime) {
+                return reason
+            }
+        }
+    }
+
+    /// Executes the next opcode, or halts the runtime if that would exceed `max_steps`
+    fn limited_step(&mut self, runtime: &mut Runtime) -> Result<(), ExitReason> {
+        let state = self.state_mut();
+        if let Some(max) = state.max_steps {
+            state.step_count += 1;
+            if state.step_count > max {
+                return Err(ExitFatal::Other("step limit exceeded".into()).into())
+            }
+        }
+        runtime.step(self).map_err(|e| match e {
+            Capture::Exit(s) => s,
+            Capture::Trap(_) => unreachable!("Trap is Infallible"),
+        })
+    }
+
+    /// Executes the call/create step by step, recording the operands of the arithmetic which
+    /// caused it to panic, if it reverts with an over/underflow
+    fn overflow_execute(&mut self, runtime: &mut Runtime) -> ExitReason {
+        let mut tracker = OverflowTracker::default();
+        loop {
+            if let Some((op, stack)) = runtime.machine().inspect() {
+                tracker.step(op, stack);
+            }
+            if let Err(reason) = self.limited_step(runtime) {
+                self.record_overflow(runtime, &reason, &tracker);
+                return reason
+            }
+        }
+    }
+
+    /// Records the overflow explaining the exit of the frame, if it panicked with an
+    /// over/underflow
+    fn record_overflow(
+        &mut self,
+        runtime: &Runtime,
+        reason: &ExitReason,
+        tracker: &OverflowTracker,
+    ) {
+        // only the innermost frame knows the operands, outer frames merely bubble up the panic
+        if matches!(reason, ExitReason::Revert(_)) &&
+            self.state().arithmetic_overflow.is_none() &&
+            foundry_utils::is_arithmetic_panic(&runtime.machine().return_value())
+        {
+            self.state_mut().arithmetic_overflow = tracker.candidate;
+        }
+    }
+
+    /// Executes the call/create while also tracking the state of the machine (including opcodes)
+    fn debug_execute(
+        &mut self,
+        runtime: &mut Runtime,
+        address: Address,
+        code: Rc<Vec<u8>>,
+        creation: bool,
+    ) -> ExitReason {
+        let depth = if let Some(depth) = self.state().metadata().depth() { depth + 1 } else { 0 };
 
         match self.debug_run(runtime, address, depth, code, creation) {
             Capture::Exit(s) => s,
@@ -954,6 +1727,15 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
         }
     }
 
+    /// Pushes `step` unless the transaction already recorded `max_debug_steps` steps
+    fn record_debug_step(&mut self, steps: &mut Vec<DebugStep>, step: DebugStep) {
+        let state = self.state_mut();
+        if state.max_debug_steps.map_or(true, |max| state.debug_step_count < max) {
+            state.debug_step_count += 1;
+            steps.push(step);
+        }
+    }
+
     /// Does *not* actually perform a step, just records the debug information for the step
     fn debug_step(
         &mut self,
@@ -964,6 +1746,7 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
     ) -> bool {
         // grab the pc, opcode and stack
         let pc = runtime.machine().position().as_ref().map(|p| *p).unwrap_or_default();
+        self.state_mut().current_pc = Some(pc);
         let mut push_bytes = None;
 
         if let Some((op, stack)) = runtime.machine().inspect() {
@@ -985,15 +1768,18 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
             let mut stack = stack.data().clone();
             stack.reverse();
             // push the step into the vector
-            steps.push(DebugStep {
-                pc,
-                stack,
-                memory: runtime.machine().memory().clone(),
-                op: wrapped_op,
-                push_bytes,
-                ic: *pc_ic.get(&pc).as_ref().copied().unwrap_or(&0usize),
-                total_gas_used: self.handler.used_gas(),
-            });
+            self.record_debug_step(
+                steps,
+                DebugStep {
+                    pc,
+                    stack,
+                    memory: runtime.machine().memory().clone(),
+                    op: wrapped_op,
+                    push_bytes,
+                    ic: *pc_ic.get(&pc).as_ref().copied().unwrap_or(&0usize),
+                    total_gas_used: self.handler.used_gas(),
+                },
+            );
             match op {
                 Opcode::CREATE |
                 Opcode::CREATE2 |
@@ -1012,15 +1798,18 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
             // failure case.
             let mut stack = runtime.machine().stack().data().clone();
             stack.reverse();
-            steps.push(DebugStep {
-                pc,
-                stack,
-                memory: runtime.machine().memory().clone(),
-                op: OpCode::from(Opcode::INVALID),
-                push_bytes,
-                ic: *pc_ic.get(&pc).as_ref().copied().unwrap_or(&0usize),
-                total_gas_used: self.handler.used_gas(),
-            });
+            self.record_debug_step(
+                steps,
+                DebugStep {
+                    pc,
+                    stack,
+                    memory: runtime.machine().memory().clone(),
+                    op: OpCode::from(Opcode::INVALID),
+                    push_bytes,
+                    ic: *pc_ic.get(&pc).as_ref().copied().unwrap_or(&0usize),
+                    total_gas_used: self.handler.used_gas(),
+                },
+            );
             true
         }
     }
@@ -1036,6 +1825,7 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
         let mut done = false;
         let mut res = Capture::Exit(ExitReason::Succeed(ExitSucceed::Returned));
         let mut steps = Vec::new();
+        let mut tracker = self.state().overflow_tracing.then(OverflowTracker::default);
         // grab the debug instruction pointers for either construct or runtime bytecode
         let dip = if creation {
             &mut self.state_mut().debug_instruction_pointers.0
@@ -1089,12 +1879,19 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
                 );
                 steps = Vec::new();
             }
+            if let (Some(tracker), Some((op, stack))) = (&mut tracker, runtime.machine().inspect())
+            {
+                tracker.step(op, stack);
+            }
             // actually executes the opcode step
-            let r = runtime.step(self);
+            let r = self.limited_step(runtime);
             match r {
                 Ok(()) => {}
-                Err(e) => {
+                Err(reason) => {
                     done = true;
+                    if let Some(ref tracker) = tracker {
+                        self.record_overflow(runtime, &reason, tracker);
+                    }
                     // we wont hit an interrupt when we finish stepping
                     // so we have add the accumulated steps as if debug_step returned true
                     if !steps.is_empty() {
@@ -1109,10 +1906,7 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
                             },
                         );
                     }
-                    match e {
-                        Capture::Exit(s) => res = Capture::Exit(s),
-                        Capture::Trap(_) => unreachable!("Trap is Infallible"),
-                    }
+                    res = Capture::Exit(reason);
                 }
             }
         }
@@ -1235,15 +2029,20 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
 
         if let Some(depth) = self.state().metadata().depth() {
             if depth > self.config().call_stack_limit {
+                self.record_call_depth(None);
                 self.fill_trace(&trace, false, None, pre_index);
                 let _ = self.handler.exit_substate(StackExitKind::Reverted);
                 return Capture::Exit((ExitError::CallTooDeep.into(), Vec::new()))
             }
+            self.record_call_depth(Some(depth));
         }
 
         if let Some(transfer) = transfer {
+            let Transfer { source, target, value } = transfer;
             match self.state_mut().transfer(transfer) {
-                Ok(()) => (),
+                Ok(()) => {
+                    self.record_balance_change(source, target, value, BalanceChangeReason::Call)
+                }
                 Err(e) => {
                     self.fill_trace(&trace, false, None, pre_index);
                     let _ = self.handler.exit_substate(StackExitKind::Reverted);
@@ -1252,6 +2051,14 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
             }
         }
 
+        // stubbed precompiles short-circuit the actual implementation
+        if let Some(stub) = self.state().precompile_stubs.get(&code_address).cloned() {
+            let output = stub(&input);
+            self.fill_trace(&trace, true, Some(output.clone()), pre_index);
+            let _ = self.handler.exit_substate(StackExitKind::Succeeded);
+            return Capture::Exit((ExitReason::Succeed(ExitSucceed::Returned), output))
+        }
+
         if let Some(result) = self.handler.precompiles().execute(
             code_address,
             &input,
@@ -1299,10 +2106,14 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
             let code = Rc::new(code);
             runtime = Runtime::new(code.clone(), Rc::new(input), context, &config);
             self.debug_execute(&mut runtime, code_address, code, false)
+        } else if self.state().overflow_tracing {
+            runtime = Runtime::new(Rc::new(code), Rc::new(input), context, &config);
+            self.overflow_execute(&mut runtime)
         } else {
             runtime = Runtime::new(Rc::new(code), Rc::new(input), context, &config);
             self.execute(&mut runtime)
         };
+        self.record_memory_gas(U256::from(runtime.machine().memory().effective_len()));
 
         // // log::debug!(target: "evm", "Call execution using address {}: {:?}", code_address,
         // reason);
@@ -1321,6 +2132,7 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
             ExitReason::Revert(e) => {
                 self.fill_trace(&trace, false, Some(runtime.machine().return_value()), pre_index);
                 let _ = self.handler.exit_substate(StackExitKind::Reverted);
+                self.record_revert(runtime.context().address, runtime.machine().return_value());
                 Capture::Exit((ExitReason::Revert(e), runtime.machine().return_value()))
             }
             ExitReason::Fatal(e) => {
@@ -1377,12 +2189,13 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
 
         if let Some(depth) = self.state().metadata().depth() {
             if depth > self.config().call_stack_limit {
+                self.record_call_depth(None);
                 self.fill_trace(&trace, false, None, pre_index);
                 return Capture::Exit((ExitError::CallTooDeep.into(), None, Vec::new()))
             }
         }
 
-        if self.balance(caller) < value {
+        if self.state().balance_checks && self.balance(caller) < value {
             self.fill_trace(&trace, false, None, pre_index);
             return Capture::Exit((ExitError::OutOfFund.into(), None, Vec::new()))
         }
@@ -1408,6 +2221,8 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
         self.state_mut().inc_nonce(caller);
 
         self.handler.enter_substate(gas_limit, false);
+        let depth = self.state().metadata().depth();
+        self.record_call_depth(depth);
 
         {
             if self.code_size(address) != U256::zero() {
@@ -1428,7 +2243,9 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
         let context = Context { address, caller, apparent_value: value };
         let transfer = Transfer { source: caller, target: address, value };
         match self.state_mut().transfer(transfer) {
-            Ok(()) => (),
+            Ok(()) => {
+                self.record_balance_change(caller, address, value, BalanceChangeReason::Create)
+            }
             Err(e) => {
                 self.fill_trace(&trace, false, None, pre_index);
                 let _ = self.handler.exit_substate(StackExitKind::Reverted);
@@ -1446,10 +2263,14 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
             let code = Rc::new(init_code);
             runtime = Runtime::new(code.clone(), Rc::new(Vec::new()), context, &config);
             self.debug_execute(&mut runtime, address, code, true)
+        } else if self.state().overflow_tracing {
+            runtime = Runtime::new(Rc::new(init_code), Rc::new(Vec::new()), context, &config);
+            self.overflow_execute(&mut runtime)
         } else {
             runtime = Runtime::new(Rc::new(init_code), Rc::new(Vec::new()), context, &config);
             self.execute(&mut runtime)
         };
+        self.record_memory_gas(U256::from(runtime.machine().memory().effective_len()));
         // log::debug!(target: "evm", "Create execution using address {}: {:?}", address, reason);
 
         match reason {
@@ -1502,6 +2323,7 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> CheatcodeStackExecutor<'a, 'b, B, P>
             ExitReason::Revert(e) => {
                 self.fill_trace(&trace, false, Some(runtime.machine().return_value()), pre_index);
                 let _ = self.handler.exit_substate(StackExitKind::Reverted);
+                self.record_revert(address, runtime.machine().return_value());
                 Capture::Exit((ExitReason::Revert(e), None, runtime.machine().return_value()))
             }
             ExitReason::Fatal(e) => {
@@ -1588,6 +2410,53 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> Handler for CheatcodeStackExecutor<'a
                 }
             }
 
+            if let Some(calldata_log) = self.state_mut().calldata_log.as_mut() {
+                let kind = if is_static {
+                    CallKind::StaticCall
+                } else if new_context.address == code_address {
+                    CallKind::Call
+                } else if new_transfer.is_some() {
+                    CallKind::CallCode
+                } else {
+                    CallKind::DelegateCall
+                };
+                calldata_log.push(RecordedCall {
+                    kind,
+                    caller: new_context.caller,
+                    callee: code_address,
+                    selector: input.get(..4).map(|s| s.try_into().unwrap()),
+                    calldata: input.clone(),
+                    value: new_context.apparent_value,
+                });
+            }
+
+            // a delegatecall to code which is already running in the same storage context, e.g.
+            // a proxy and an implementation delegating to each other, recurses until out of gas
+            let is_delegate_call = new_transfer.is_none() && new_context.address != code_address;
+            if let Some(frames) = self.state().call_frames.as_ref().filter(|_| is_delegate_call) {
+                let chain = frames
+                    .iter()
+                    .rev()
+                    .take_while(|(storage, _)| *storage == new_context.address)
+                    .map(|(_, code)| *code)
+                    .collect::<Vec<_>>();
+                if let Some(pos) = chain.iter().position(|code| *code == code_address) {
+                    let cycle = chain[..=pos]
+                        .iter()
+                        .rev()
+                        .copied()
+                        .chain(std::iter::once(code_address))
+                        .collect::<Vec<_>>();
+                    let msg = cycle
+                        .iter()
+                        .map(|address| format!("{:?}", address))
+                        .collect::<Vec<_>>()
+                        .join(" → ");
+                    self.state_mut().delegate_cycle.get_or_insert(cycle);
+                    return evm_error(&format!("delegatecall cycle detected: {}", msg))
+                }
+            }
+
             // handle mocked calls
             if let Some(mocks) = self.state().mocked_calls.get(&code_address) {
                 if let Some(mock_retdata) = mocks.get(&input) {
@@ -1596,7 +2465,7 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> Handler for CheatcodeStackExecutor<'a
                         mock_retdata.clone(),
                     ))
                 } else if let Some((_, mock_retdata)) =
-                    mocks.iter().find(|(mock, _)| *mock == &input[..mock.len()])
+                    mocks.iter().find(|(mock, _)| input.starts_with(mock))
                 {
                     return Capture::Exit((
                         ExitReason::Succeed(ExitSucceed::Returned),
@@ -1605,6 +2474,12 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> Handler for CheatcodeStackExecutor<'a
                 }
             }
 
+            if let Some(frames) = self.state_mut().call_frames.as_mut() {
+                frames.push((new_context.address, code_address));
+            }
+
+            let hook = self.state().call_end_hook.clone().map(|hook| (hook, input.clone()));
+
             // perform the call
             let res = self.call_inner(
                 code_address,
@@ -1617,6 +2492,17 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> Handler for CheatcodeStackExecutor<'a
                 new_context,
             );
 
+            if let Some(frames) = self.state_mut().call_frames.as_mut() {
+                frames.pop();
+            }
+
+            let res = match (res, hook) {
+                (Capture::Exit((status, retdata)), Some((hook, input))) => {
+                    Capture::Exit(hook(code_address, &input, status, retdata))
+                }
+                (res, _) => res,
+            };
+
             // if we set the origin, now we should reset to previous
             self.state_mut().backend.cheats.origin = prev_origin;
 
@@ -1786,7 +2672,19 @@ impl<'a, 'b, B: Backend, P: PrecompileSet> Handler for CheatcodeStackExecutor<'a
     }
 
     fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError> {
-        self.handler.mark_delete(address, target)
+        let balance = self.balance(address);
+        self.handler.mark_delete(address, target)?;
+        let reason = BalanceChangeReason::SelfDestruct;
+        if target != address {
+            self.record_balance_change(address, target, balance, reason);
+        } else if let Some(balance_log) = self.state_mut().balance_log.as_mut() {
+            // a contract which destructs itself burns its balance
+            if !balance.is_zero() {
+                let delta = -I256::from_raw(balance);
+                balance_log.push(BalanceChange { address, delta, reason });
+            }
+        }
+        Ok(())
     }
 
     fn create(
@@ -1869,13 +2767,48 @@ mod tests {
     use crate::{
         call_tracing::ExecutionInfo,
         fuzz::FuzzedExecutor,
-        sputnik::helpers::{vm, vm_no_limit, vm_tracing},
+        sputnik::{
+            cheatcodes::memory_stackstate_owned::selector_call_counts,
+            helpers::{
+                new_backend, new_vicinity, vm, vm_debug, vm_no_limit, vm_tracing, TestSputnikVM,
+                CFG, GAS_LIMIT, VICINITY,
+            },
+            PRECOMPILES_MAP,
+        },
         test_helpers::COMPILED,
         Evm,
     };
+    use sputnik::backend::{MemoryBackend, MemoryVicinity};
 
     use super::*;
 
+    #[test]
+    fn decodes_ds_test_logs() {
+        use HevmConsoleEvents::*;
+
+        let log = |signature: &str, tokens: &[Token]| RawLog {
+            topics: vec![H256::from(utils::keccak256(signature))],
+            data: ethers::abi::encode(tokens),
+        };
+        let (key, addr) = (Token::String("key".to_string()), Address::random());
+        let logs = [
+            log("log(string)", &[Token::String("hi".to_string())]),
+            log("log_named_uint(string,uint256)", &[key.clone(), Token::Uint(1.into())]),
+            log("Transfer(address,address,uint256)", &[Token::Uint(1.into())]),
+            log("log_named_address(string,address)", &[key.clone(), Token::Address(addr)]),
+            log("log_named_int(string,int256)", &[key, Token::Int(I256::from(-1).into_raw())]),
+        ];
+
+        let decoded = decode_ds_test_logs(&logs);
+        assert_eq!(decoded.len(), 4);
+        assert!(matches!(&decoded[0], LogFilter(inner) if inner.0 == "hi"));
+        assert!(matches!(&decoded[1], LogNamedUintFilter(inner) if inner.val == 1.into()));
+        assert!(matches!(&decoded[2], LogNamedAddressFilter(inner) if inner.val == addr));
+        assert!(
+            matches!(&decoded[3], LogNamedIntFilter(inner) if inner.key == "key" && inner.val == I256::from(-1))
+        );
+    }
+
     #[test]
     fn ds_test_logs() {
         let mut evm = vm();
@@ -1950,6 +2883,27 @@ mod tests {
         assert_eq!(logs, expected);
     }
 
+    #[test]
+    fn log_verbosity() {
+        let mut evm = vm();
+
+        let compiled = COMPILED.find("ConsoleLogs").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let mut logs_at = |level: u8| {
+            evm.set_log_verbosity(level);
+            let (_, _, _, logs) = evm
+                .call::<(), _, _>(Address::zero(), addr, "test_log_levels()", (), 0.into(), None)
+                .unwrap();
+            logs
+        };
+        assert_eq!(logs_at(2), ["[info] deploying", "done"]);
+        assert_eq!(logs_at(3), ["[info] deploying", "[debug] salt, 1", "done"]);
+        assert_eq!(logs_at(4).len(), 4);
+        assert!(logs_at(1).is_empty());
+    }
+
     #[test]
     fn console_logs_types() {
         let mut evm = vm();
@@ -2114,6 +3068,980 @@ mod tests {
         }
     }
 
+    #[test]
+    fn stubbed_ecrecover() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("SignatureGated").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let args = ([1u8; 32], 27u8, [2u8; 32], [3u8; 32]);
+        let sig = "gated(bytes32,uint8,bytes32,bytes32)(bool)";
+
+        let err = evm
+            .call::<bool, _, _>(Address::zero(), addr, sig, args, 0.into(), compiled.abi)
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::EvmError::Execution { reason, .. } if reason == "invalid signature")
+        );
+
+        let signer = Address::from_low_u64_be(0x1337);
+        evm.stub_ecrecover(move |_| H256::from(signer).as_bytes().to_vec());
+        let (ok, _, _, _) = evm
+            .call::<bool, _, _>(Address::zero(), addr, sig, args, 0.into(), compiled.abi)
+            .unwrap();
+        assert!(ok);
+
+        evm.clear_precompile_stubs();
+        assert!(evm
+            .call::<bool, _, _>(Address::zero(), addr, sig, args, 0.into(), compiled.abi)
+            .is_err());
+    }
+
+    #[test]
+    fn records_calldata() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("CallKinds").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let (target, _, _, _) = evm
+            .call::<Address, _, _>(Address::zero(), addr, "target()(address)", (), 0.into(), None)
+            .unwrap();
+        evm.set_balance(addr, 1.into());
+
+        let calldata = utils::id("run()").to_vec().into();
+        let ((_, status, _, _), calls) = evm
+            .call_raw_with_calldata_log(Address::zero(), addr, calldata, 0.into(), false)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+
+        let set = utils::id("set(uint256)");
+        let set_one = [&set[..], H256::from_low_u64_be(1).as_bytes()].concat();
+        let set_two = [&set[..], H256::from_low_u64_be(2).as_bytes()].concat();
+        assert_eq!(
+            calls,
+            vec![
+                RecordedCall {
+                    kind: CallKind::Call,
+                    caller: addr,
+                    callee: target,
+                    selector: Some(set),
+                    calldata: set_one,
+                    value: 1.into(),
+                },
+                RecordedCall {
+                    kind: CallKind::StaticCall,
+                    caller: addr,
+                    callee: target,
+                    selector: Some(utils::id("x()")),
+                    calldata: utils::id("x()").to_vec(),
+                    value: 0.into(),
+                },
+                RecordedCall {
+                    kind: CallKind::DelegateCall,
+                    caller: Address::zero(),
+                    callee: target,
+                    selector: Some(set),
+                    calldata: set_two,
+                    value: 0.into(),
+                },
+            ]
+        );
+
+        // recording is only enabled for the duration of the call
+        assert!(evm.state().calldata_log.is_none());
+    }
+
+    #[test]
+    fn counts_selector_calls() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("RepeatedCalls").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let calldata = utils::id("run()").to_vec().into();
+        let ((_, status, _, _), calls) = evm
+            .call_raw_with_calldata_log(Address::zero(), addr, calldata, 0.into(), false)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(
+            selector_call_counts(&calls),
+            BTreeMap::from([(utils::id("set(uint256)"), 3), (utils::id("x()"), 1)])
+        );
+    }
+
+    fn reason<S, E: Evm<S>>(evm: &mut E, to: Address, sig: &str, args: (U256, U256)) -> String {
+        let err = evm.call::<U256, _, _>(Address::zero(), to, sig, args, 0.into(), None);
+        match err.unwrap_err() {
+            crate::EvmError::Execution { reason, .. } => reason,
+            _ => panic!("unexpected error variant"),
+        }
+    }
+
+    #[test]
+    fn explains_arithmetic_overflow() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Arithmetic").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        // only explained if enabled
+        assert_eq!(
+            reason(&mut evm, addr, "add(uint256,uint256)(uint256)", (U256::MAX, 1.into())),
+            "Arithmetic over/underflow"
+        );
+
+        evm.set_overflow_tracing(true);
+        assert_eq!(
+            reason(&mut evm, addr, "add(uint256,uint256)(uint256)", (U256::MAX, 1.into())),
+            format!("Arithmetic over/underflow (overflow in addition: {} + 1)", U256::MAX)
+        );
+        assert_eq!(
+            reason(&mut evm, addr, "sub(uint256,uint256)(uint256)", (0.into(), 1.into())),
+            "Arithmetic over/underflow (underflow in subtraction: 0 - 1)"
+        );
+        assert_eq!(
+            reason(&mut evm, addr, "mul(uint256,uint256)(uint256)", (U256::MAX, 2.into())),
+            format!("Arithmetic over/underflow (overflow in multiplication: {} * 2)", U256::MAX)
+        );
+    }
+
+    #[test]
+    fn explains_arithmetic_overflow_after_loop() {
+        // debugging executes the calls step by step as well, so the overflow is still explained
+        for mut evm in [vm(), vm_debug(true)] {
+            let compiled = COMPILED.find("Arithmetic").expect("could not find contract");
+            let (addr, _, _, _) = evm
+                .deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+                .unwrap();
+            evm.set_overflow_tracing(true);
+
+            // the comparisons of the loop do not explain the underflow after it
+            assert_eq!(
+                reason(
+                    &mut evm,
+                    addr,
+                    "sumThenSub(uint256,uint256)(uint256)",
+                    (4.into(), 10.into())
+                ),
+                "Arithmetic over/underflow (underflow in subtraction: 6 - 10)"
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_panic_reason() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Arithmetic").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        assert_eq!(
+            reason(&mut evm, addr, "div(uint256,uint256)(uint256)", (1.into(), 0.into())),
+            "Division or modulo by 0"
+        );
+    }
+
+    #[test]
+    fn call_with_other_chain_id() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Permit").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        // sign a permit for the executor's chain
+        let struct_hash = [7u8; 32];
+        let (digest, _, _, _) = evm
+            .call::<H256, _, _>(
+                Address::zero(),
+                addr,
+                "digest(bytes32)(bytes32)",
+                struct_hash,
+                0.into(),
+                None,
+            )
+            .unwrap();
+        let wallet = LocalWallet::from(SigningKey::from_bytes(&[1u8; 32]).unwrap());
+        let sig = wallet.sign_hash(digest, false);
+        let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+        sig.r.to_big_endian(&mut r);
+        sig.s.to_big_endian(&mut s);
+        let args = (wallet.address(), struct_hash, sig.v as u8, r, s);
+        let permit = "permit(address,bytes32,uint8,bytes32,bytes32)";
+
+        let err = evm
+            .call_with_chain_id::<(), _, _>(
+                Address::zero(),
+                addr,
+                permit,
+                args,
+                0.into(),
+                compiled.abi,
+                1337,
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::EvmError::Execution { reason, .. } if reason == "invalid signature")
+        );
+        assert_eq!(evm.state().backend.cheats.chain_id, None);
+
+        evm.call::<(), _, _>(Address::zero(), addr, permit, args, 0.into(), compiled.abi).unwrap();
+    }
+
+    #[test]
+    fn storage_accesses() {
+        use std::collections::BTreeSet;
+
+        let mut evm = vm();
+        let compiled = COMPILED.find("RecordAccess").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let calldata = utils::id("record()").to_vec().into();
+        let ((retdata, _, _, _), accesses) = evm
+            .call_raw_with_storage_accesses(Address::zero(), addr, calldata, 0.into(), false)
+            .unwrap();
+        let target = Address::from_slice(&retdata[12..32]);
+
+        let expected = BTreeMap::from([
+            (addr, BTreeSet::from([H256::from_low_u64_be(1)])),
+            (target, BTreeSet::from([H256::from_low_u64_be(2)])),
+        ]);
+        assert_eq!(accesses.writes, expected);
+        // writes also load the slot
+        assert_eq!(accesses.reads, expected);
+        assert!(accesses.read_only().is_empty());
+        assert!(evm.state().accesses.is_none());
+
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let calldata = utils::id("greeting()").to_vec().into();
+        let (_, accesses) = evm
+            .call_raw_with_storage_accesses(Address::zero(), addr, calldata, 0.into(), true)
+            .unwrap();
+        assert!(accesses.writes.is_empty());
+        assert_eq!(accesses.read_only(), BTreeMap::from([(addr, BTreeSet::from([H256::zero()]))]));
+    }
+
+    #[test]
+    fn call_across_configs() {
+        // BASEFEE was introduced in London
+        let (berlin, london) = (Config::berlin(), Config::london());
+
+        let mut evm = vm();
+        let compiled = COMPILED.find("BaseFee").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let calldata: ethers::types::Bytes = utils::id("baseFee()").to_vec().into();
+        let results = evm
+            .call_raw_across_configs(Address::zero(), addr, calldata, 0.into(), &[&berlin, &london])
+            .unwrap();
+        assert!(matches!(results[0].1, ExitReason::Error(_)));
+        assert!(matches!(results[1].1, ExitReason::Succeed(_)));
+
+        // the executor's settings carry over, so impersonated accounts are funded
+        let whale = Address::from_low_u64_be(0xdead);
+        evm.impersonate(whale);
+        let results = evm
+            .call_raw_across_configs(
+                whale,
+                Address::from_low_u64_be(0xbeef),
+                Default::default(),
+                U256::exp10(18),
+                &[&berlin, &london],
+            )
+            .unwrap();
+        assert!(results.iter().all(|(_, status, _, _)| matches!(status, ExitReason::Succeed(_))));
+    }
+
+    #[test]
+    fn block_hash_window() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("BlockHashWindow").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let cheats = &mut evm.state_mut().backend.cheats;
+        cheats.block_hash_window = true;
+        cheats.block_number = Some(1000.into());
+        evm.call::<(), _, _>(Address::zero(), addr, "check()", (), 0.into(), compiled.abi).unwrap();
+
+        // pseudo-hashes are deterministic
+        let func = "hash(uint256)(bytes32)";
+        let (hash, _, _, _) = evm
+            .call::<H256, _, _>(Address::zero(), addr, func, U256::from(999), 0.into(), None)
+            .unwrap();
+        assert_eq!(hash, H256::from(utils::keccak256(H256::from_low_u64_be(999))));
+    }
+
+    #[test]
+    fn set_block_hash() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("BlockHashWindow").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let seeded = H256::random();
+        evm.set_block_hash(7.into(), seeded);
+        let mut hash = |number: u64| {
+            let func = "hash(uint256)(bytes32)";
+            evm.call::<H256, _, _>(Address::zero(), addr, func, U256::from(number), 0.into(), None)
+                .unwrap()
+                .0
+        };
+        assert_eq!(hash(7), seeded);
+        assert_eq!(hash(8), H256::zero());
+    }
+
+    #[test]
+    fn warp_and_roll_persist() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Clock").expect("could not find contract");
+        let (clock, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        // the block values set by a call are seen by all later calls
+        let mut call = |func: &str, arg: U256| {
+            evm.call::<(), _, _>(Address::zero(), clock, func, arg, 0.into(), None).unwrap();
+        };
+        call("warp(uint256)", 1000.into());
+        call("roll(uint256)", 42.into());
+        let mut get = |func: &str| {
+            evm.call::<U256, _, _>(Address::zero(), clock, func, (), 0.into(), None).unwrap().0
+        };
+        assert_eq!(get("timestamp()(uint256)"), 1000.into());
+        assert_eq!(get("number()(uint256)"), 42.into());
+    }
+
+    #[test]
+    fn balance_log() {
+        let coinbase = Address::random();
+        let vicinity = MemoryVicinity {
+            gas_price: 3.into(),
+            block_base_fee_per_gas: 1.into(),
+            block_coinbase: coinbase,
+            ..new_vicinity()
+        };
+        let backend = new_backend(&vicinity, Default::default());
+        let mut evm = Executor::new_with_cheatcodes(
+            backend,
+            GAS_LIMIT,
+            &*CFG,
+            &*PRECOMPILES_MAP,
+            true,
+            false,
+            false,
+        );
+        let compiled = COMPILED.find("Splitter").expect("could not find contract");
+        let (splitter, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let (sender, a, b) = (Address::random(), Address::random(), Address::random());
+        evm.set_balance(sender, 100.into());
+
+        let change = |address, delta: i64, reason| BalanceChange {
+            address,
+            delta: I256::from(delta),
+            reason,
+        };
+        let (call, self_destruct) = (BalanceChangeReason::Call, BalanceChangeReason::SelfDestruct);
+        // the sender pays the gas price of 3 per gas, of which everything above the base fee of 1
+        // goes to the coinbase
+        let gas_payment = |gas: u64, calldata: &ethers::types::Bytes| {
+            let gas = (gas + foundry_utils::intrinsic_gas(calldata.as_ref())) as i64;
+            [
+                change(sender, -3 * gas, BalanceChangeReason::Gas),
+                change(coinbase, 2 * gas, BalanceChangeReason::CoinbaseTip),
+            ]
+        };
+
+        let calldata =
+            encode_function_data(&IntoFunction::into("split(address,address)"), (a, b)).unwrap();
+        let ((_, status, gas, _), log) = evm
+            .call_raw_with_balance_log(sender, splitter, calldata.clone(), 11.into(), false)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        let mut expected = vec![
+            change(sender, -11, call),
+            change(splitter, 11, call),
+            change(splitter, -5, call),
+            change(a, 5, call),
+            change(splitter, -6, call),
+            change(b, 6, call),
+        ];
+        expected.extend(gas_payment(gas, &calldata));
+        assert_eq!(log, expected);
+
+        let calldata = encode_function_data(&IntoFunction::into("destroy(address)"), a).unwrap();
+        let ((_, status, gas, _), log) = evm
+            .call_raw_with_balance_log(sender, splitter, calldata.clone(), 3.into(), false)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        let mut expected = vec![
+            change(sender, -3, call),
+            change(splitter, 3, call),
+            change(splitter, -3, self_destruct),
+            change(a, 3, self_destruct),
+        ];
+        expected.extend(gas_payment(gas, &calldata));
+        assert_eq!(log, expected);
+        assert!(evm.state().balance_log.is_none());
+    }
+
+    #[test]
+    fn revert_chain() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Forwarder").expect("could not find contract");
+        let (forwarder, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let mut get = |to: Address, func: &str| {
+            evm.call::<Address, _, _>(Address::zero(), to, func, (), 0.into(), None).unwrap().0
+        };
+        let rethrower = get(forwarder, "rethrower()(address)");
+        let thrower = get(rethrower, "thrower()(address)");
+
+        let calldata = utils::id("run()").to_vec().into();
+        let ((_, status, _, _), chain) = evm
+            .call_raw_with_revert_chain(Address::zero(), forwarder, calldata, 0.into(), false)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Revert(_)));
+        let chain = chain
+            .into_iter()
+            .map(|(addr, data)| (addr, foundry_utils::decode_revert(&data, None).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chain,
+            vec![
+                (thrower, "inner".to_string()),
+                (rethrower, "rethrown".to_string()),
+                (forwarder, "rethrown".to_string()),
+            ]
+        );
+        assert!(evm.state().revert_chain.is_none());
+    }
+
+    #[test]
+    fn gas_by_selector() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("WorkerCaller").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let calldata = utils::id("run()").to_vec().into();
+        let (_, trace) =
+            evm.call_raw_with_trace(Address::zero(), addr, calldata, 0.into(), false).unwrap();
+        let gas = trace.gas_by_selector();
+        assert_eq!(gas.len(), 3);
+        assert!(gas[&utils::id("expensive()")] > gas[&utils::id("cheap()")]);
+        assert!(gas[&utils::id("run()")] > gas[&utils::id("expensive()")]);
+    }
+
+    #[test]
+    fn call_raw_with_trace() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Forwarder").expect("could not find contract");
+        let (forwarder, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let mut get = |to: Address, func: &str| {
+            evm.call::<Address, _, _>(Address::zero(), to, func, (), 0.into(), None).unwrap().0
+        };
+        let rethrower = get(forwarder, "rethrower()(address)");
+        let thrower = get(rethrower, "thrower()(address)");
+
+        let calldata = utils::id("run()").to_vec().into();
+        let (_, trace) =
+            evm.call_raw_with_trace(Address::zero(), forwarder, calldata, 0.into(), false).unwrap();
+        let frames = trace
+            .arena
+            .iter()
+            .map(|node| (node.trace.depth, node.trace.addr, node.trace.success))
+            .collect::<Vec<_>>();
+        assert_eq!(frames, [(0, forwarder, false), (1, rethrower, false), (2, thrower, false)]);
+        assert_eq!(trace.arena[0].children, [1]);
+        assert_eq!(trace.arena[1].children, [2]);
+        assert!(!evm.tracing_enabled());
+    }
+
+    #[test]
+    fn call_end_hook() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Forwarder").expect("could not find contract");
+        let (forwarder, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let mut get = |to: Address, func: &str| {
+            evm.call::<Address, _, _>(Address::zero(), to, func, (), 0.into(), None).unwrap().0
+        };
+        let rethrower = get(forwarder, "rethrower()(address)");
+        let thrower = get(rethrower, "thrower()(address)");
+
+        // the thrower's revert is swallowed, so the rethrower never reverts
+        evm.set_call_end_hook(move |address, input, status, retdata| {
+            if address == thrower {
+                assert_eq!(input, utils::id("fail()"));
+                (ExitReason::Succeed(ExitSucceed::Returned), vec![])
+            } else {
+                (status, retdata)
+            }
+        });
+        evm.call::<(), _, _>(Address::zero(), forwarder, "run()", (), 0.into(), None).unwrap();
+
+        evm.clear_call_end_hook();
+        let err = evm
+            .call::<(), _, _>(Address::zero(), forwarder, "run()", (), 0.into(), None)
+            .unwrap_err();
+        assert!(matches!(err, EvmError::Execution { ref reason, .. } if reason == "rethrown"));
+    }
+
+    #[test]
+    fn add_call_end_hook() {
+        use std::cell::RefCell;
+
+        let mut evm = vm();
+        let compiled = COMPILED.find("Forwarder").expect("could not find contract");
+        let (forwarder, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        // the second hook sees the outcomes returned by the first one
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        evm.set_call_end_hook(|_, _, status, retdata| match status {
+            ExitReason::Revert(_) => (ExitReason::Succeed(ExitSucceed::Returned), vec![]),
+            _ => (status, retdata),
+        });
+        let seen_by_hook = seen.clone();
+        evm.add_call_end_hook(move |address, _, status, retdata| {
+            seen_by_hook.borrow_mut().push((address, status.clone()));
+            (status, retdata)
+        });
+
+        // and both run along with tracing
+        let calldata = utils::id("run()").to_vec().into();
+        let ((_, status, _, _), trace) =
+            evm.call_raw_with_trace(Address::zero(), forwarder, calldata, 0.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(trace.arena.len(), 3);
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 2);
+        assert!(seen.iter().all(|(_, status)| matches!(status, ExitReason::Succeed(_))));
+    }
+
+    #[test]
+    fn expect_revert() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Forwarder").expect("could not find contract");
+        let (forwarder, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let revert_data = |reason: &str| {
+            let mut data = vec![8, 195, 121, 160];
+            data.extend(ethers::abi::encode(&[Token::String(reason.to_string())]));
+            ethers::types::Bytes::from(data)
+        };
+        let calldata: ethers::types::Bytes = utils::id("run()").to_vec().into();
+
+        // matching revert data, the caught revert of the subcall is not checked
+        evm.expect_revert(Some(revert_data("rethrown")));
+        let (retdata, status, _, _) =
+            evm.call_raw(Address::zero(), forwarder, calldata.clone(), 0.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(retdata, revert_data("rethrown"));
+
+        // any revert
+        evm.expect_revert(None);
+        let (_, status, _, _) =
+            evm.call_raw(Address::zero(), forwarder, calldata.clone(), 0.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+
+        // the expectation only applies to a single call
+        let (_, status, _, _) =
+            evm.call_raw(Address::zero(), forwarder, calldata.clone(), 0.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Revert(_)));
+
+        // mismatching revert data
+        evm.expect_revert(Some(revert_data("inner")));
+        let (retdata, status, _, _) =
+            evm.call_raw(Address::zero(), forwarder, calldata, 0.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Revert(_)));
+        let reason = foundry_utils::decode_revert(retdata.as_ref(), None).unwrap();
+        assert!(reason.starts_with("Error data != expected error data"), "{}", reason);
+
+        // no revert
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (greeter, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        evm.expect_revert(None);
+        let calldata = utils::id("gm()").to_vec().into();
+        let (retdata, status, _, _) =
+            evm.call_raw(Address::zero(), greeter, calldata, 0.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Revert(_)));
+        assert_eq!(
+            foundry_utils::decode_revert(retdata.as_ref(), None).unwrap(),
+            "Expected revert did not revert"
+        );
+    }
+
+    #[test]
+    fn clone_executor() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("CallTarget").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        evm.write_storage(addr, 0.into(), 1.into());
+
+        let mut cloned = evm.clone();
+        assert_eq!(cloned.read_storage(addr, 0.into()), 1.into());
+        cloned.write_storage(addr, 0.into(), 2.into());
+        let (x, _, _, _) = cloned
+            .call::<U256, _, _>(Address::zero(), addr, "x()(uint256)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(x, 2.into());
+
+        assert_eq!(evm.read_storage(addr, 0.into()), 1.into());
+        let (x, _, _, _) = evm
+            .call::<U256, _, _>(Address::zero(), addr, "x()(uint256)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(x, 1.into());
+    }
+
+    #[test]
+    fn call_raw_uncommitted() {
+        let mut evm = vm();
+        let (sender, recipient) = (Address::random(), Address::random());
+        evm.set_balance(sender, 100.into());
+
+        let ((_, status, _, _), post_state) =
+            evm.call_raw_uncommitted(sender, recipient, Default::default(), 40.into()).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(post_state.state().basic(sender).balance, 60.into());
+        assert_eq!(post_state.state().basic(recipient).balance, 40.into());
+
+        assert_eq!(evm.state().basic(sender).balance, 100.into());
+        assert_eq!(evm.state().basic(recipient).balance, 0.into());
+    }
+
+    #[test]
+    fn deploy_uncommitted() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let bytecode = compiled.bytecode().unwrap().clone();
+
+        let ((addr, _, gas, _), post_state) =
+            evm.deploy_uncommitted(Address::zero(), bytecode.clone(), 0.into()).unwrap();
+        assert!(gas > 0);
+        let runtime_code = compiled.bin_runtime.unwrap().clone().into_bytes().unwrap();
+        assert_eq!(post_state.code(addr), runtime_code.to_vec());
+        assert!(evm.code(addr).is_empty());
+        assert_eq!(evm.get_nonce(Address::zero()), 0);
+
+        // the post-state can be adopted, like a committed deployment
+        evm = post_state;
+        let (deployed, _, _, _) = evm.deploy(Address::zero(), bytecode, 0.into()).unwrap();
+        assert!(!evm.code(addr).is_empty());
+        assert_ne!(deployed, addr);
+    }
+
+    #[test]
+    fn disable_balance_checks() {
+        let mut evm = vm();
+        let (sender, recipient) = (Address::random(), Address::random());
+        let (_, status, _, _) =
+            evm.call_raw(sender, recipient, Default::default(), 100.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Error(ExitError::OutOfFund)));
+
+        evm.set_balance_checks(false);
+        let (_, status, _, _) =
+            evm.call_raw(sender, recipient, Default::default(), 100.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(evm.state().basic(recipient).balance, 100.into());
+        assert_eq!(evm.state().basic(sender).balance, 0.into());
+    }
+
+    #[test]
+    fn step_limit() {
+        let mut evm = vm();
+        let (looping, finite) = (Address::random(), Address::random());
+        // JUMPDEST, PUSH1 0, JUMP
+        evm.initialize_contracts([(looping, vec![0x5b, 0x60, 0x00, 0x56].into())]);
+        // PUSH1 2, PUSH1 3, ADD, STOP
+        evm.initialize_contracts([(finite, vec![0x60, 0x02, 0x60, 0x03, 0x01, 0x00].into())]);
+
+        evm.set_step_limit(Some(100));
+        let err = evm
+            .call_raw(Address::zero(), looping, Default::default(), 0.into(), false)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::EvmError>(),
+            Some(crate::EvmError::StepLimitExceeded { limit: 100, .. })
+        ));
+
+        // the steps are counted per transaction
+        evm.set_step_limit(Some(4));
+        for _ in 0..2 {
+            let (_, status, _, _) =
+                evm.call_raw(Address::zero(), finite, Default::default(), 0.into(), false).unwrap();
+            assert!(matches!(status, ExitReason::Succeed(_)));
+        }
+        evm.set_step_limit(Some(3));
+        assert!(evm
+            .call_raw(Address::zero(), finite, Default::default(), 0.into(), false)
+            .is_err());
+    }
+
+    #[test]
+    fn debug_steps() {
+        let mut evm = vm();
+        let addr = Address::random();
+        // PUSH1 2, PUSH1 3, ADD, PUSH1 4, MUL, STOP
+        let code = vec![0x60, 0x02, 0x60, 0x03, 0x01, 0x60, 0x04, 0x02, 0x00];
+        evm.initialize_contracts([(addr, code.into())]);
+
+        let ((_, status, _, _), steps) = evm
+            .call_raw_with_debug_steps(Address::zero(), addr, Default::default(), 0.into(), None)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        let ops = steps.iter().map(|step| step.op.0).collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                Opcode::PUSH1,
+                Opcode::PUSH1,
+                Opcode::ADD,
+                Opcode::PUSH1,
+                Opcode::MUL,
+                Opcode::STOP
+            ]
+        );
+        assert_eq!(steps.iter().map(|step| step.pc).collect::<Vec<_>>(), vec![0, 2, 4, 5, 7, 8]);
+        assert_eq!(steps[4].stack, vec![H256::from_low_u64_be(4), H256::from_low_u64_be(5)]);
+        assert!(steps.windows(2).all(|w| w[0].total_gas_used <= w[1].total_gas_used));
+        // debugging is only enabled for the call
+        assert!(!evm.executor.state().debug_enabled);
+
+        // execution continues after the cap is reached
+        let ((_, status, _, _), steps) = evm
+            .call_raw_with_debug_steps(Address::zero(), addr, Default::default(), 0.into(), Some(3))
+            .unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(steps.len(), 3);
+    }
+
+    #[test]
+    fn call_raw_with_origin() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Origin").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let (from, origin) = (Address::random(), Address::random());
+        let calldata: ethers::types::Bytes = utils::id("origin()").to_vec().into();
+        let address_pair = [ethers::abi::ParamType::Address, ethers::abi::ParamType::Address];
+        let (retdata, _, _, _) =
+            evm.call_raw_with_origin(from, origin, addr, calldata.clone(), 0.into()).unwrap();
+        let tokens = ethers::abi::decode(&address_pair, &retdata).unwrap();
+        assert_eq!(tokens, vec![Token::Address(origin), Token::Address(from)]);
+
+        // the origin is restored
+        let (retdata, _, _, _) = evm.call_raw(from, addr, calldata, 0.into(), false).unwrap();
+        let tokens = ethers::abi::decode(&address_pair, &retdata).unwrap();
+        assert_ne!(tokens[0], Token::Address(origin));
+    }
+
+    #[test]
+    fn reset_backend() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let alice = Address::random();
+        evm.set_balance(alice, 100.into());
+        evm.executor.state_mut().backend.cheats.chain_id = Some(10.into());
+        let snapshot = evm.snapshot();
+
+        evm.reset_backend(new_backend(&*VICINITY, Default::default()), Some(5.into()));
+        assert!(evm.code(addr).is_empty());
+        assert_eq!(evm.state().basic(alice).balance, 0.into());
+        assert!(!evm.revert(snapshot));
+        let cheats = &evm.executor.state().backend.cheats;
+        assert_eq!(cheats.chain_id, Some(10.into()));
+        assert_eq!(cheats.block_number, Some(5.into()));
+
+        // the executor is still usable, cheatcodes included
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        evm.call::<(), _, _>(Address::zero(), addr, "gm()", (), 0.into(), None).unwrap();
+        assert!(!evm.code(*CHEATCODE_ADDRESS).is_empty());
+    }
+
+    #[test]
+    fn mock_call() {
+        fn double_price(
+            evm: &mut TestSputnikVM<'_, MemoryBackend<'_>>,
+            consumer: Address,
+            id: u64,
+        ) -> u64 {
+            let sig = "doublePrice(uint256)(uint256)";
+            let (price, _, _, _) = evm
+                .call::<U256, _, _>(Address::zero(), consumer, sig, U256::from(id), 0.into(), None)
+                .unwrap();
+            price.as_u64()
+        }
+
+        let mut evm = vm();
+        let compiled = COMPILED.find("PriceConsumer").expect("could not find contract");
+        let (consumer, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let (oracle, _, _, _) = evm
+            .call::<Address, _, _>(
+                Address::zero(),
+                consumer,
+                "oracle()(address)",
+                (),
+                0.into(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(double_price(&mut evm, consumer, 1), 2);
+
+        // all calls of the function
+        let price = IntoFunction::into("price(uint256)");
+        let selector = price.short_signature().to_vec();
+        evm.mock_call(
+            oracle,
+            selector.into(),
+            ethers::abi::encode(&[Token::Uint(5.into())]).into(),
+        );
+        assert_eq!(double_price(&mut evm, consumer, 1), 10);
+        assert_eq!(double_price(&mut evm, consumer, 2), 10);
+
+        // exact calldata takes precedence
+        let calldata = encode_function_data(&price, U256::from(2)).unwrap();
+        evm.mock_call(oracle, calldata, ethers::abi::encode(&[Token::Uint(7.into())]).into());
+        assert_eq!(double_price(&mut evm, consumer, 1), 10);
+        assert_eq!(double_price(&mut evm, consumer, 2), 14);
+
+        evm.clear_mocked_calls();
+        assert_eq!(double_price(&mut evm, consumer, 2), 2);
+    }
+
+    #[test]
+    fn gas_breakdown() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("MemoryExpansion").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let mut expand = |offset: u64| {
+            let func = IntoFunction::into("expand(uint256)");
+            let calldata = encode_function_data(&func, U256::from(offset)).unwrap();
+            let ((_, status, gas, _), breakdown) = evm
+                .call_raw_with_gas_breakdown(
+                    Address::zero(),
+                    addr,
+                    calldata.clone(),
+                    0.into(),
+                    false,
+                )
+                .unwrap();
+            assert!(matches!(status, ExitReason::Succeed(_)));
+            assert_eq!(breakdown.execution + breakdown.memory, gas);
+            assert_eq!(breakdown.intrinsic, foundry_utils::intrinsic_gas(&calldata));
+            breakdown
+        };
+        // only the free memory pointer at 0x40 is written to
+        let small = expand(0);
+        assert_eq!(small.memory, 3 * 3);
+        // expands the memory to 128 words
+        let large = expand(4064);
+        assert_eq!(large.memory, 3 * 128 + 128 * 128 / 512);
+        assert_eq!(small.execution, large.execution);
+        assert!(evm.state().memory_gas.is_none());
+    }
+
+    #[test]
+    fn delegate_cycles() {
+        let mut evm = vm();
+        let mut deploy = |name: &str| {
+            let compiled = COMPILED.find(name).expect("could not find contract");
+            let bytecode = compiled.bytecode().unwrap().clone();
+            evm.deploy(Address::zero(), bytecode, 0.into()).unwrap().0
+        };
+        let proxy = deploy("CycleProxy");
+        let looping = deploy("LoopingImplementation");
+        let plain = deploy("PlainImplementation");
+
+        // proxy -> looping implementation -> proxy
+        let sender = Address::zero();
+        evm.call::<(), _, _>(sender, proxy, "setImplementation(address)", looping, 0.into(), None)
+            .unwrap();
+        evm.call::<(), _, _>(sender, proxy, "setNext(address)", proxy, 0.into(), None).unwrap();
+
+        let run: ethers::types::Bytes = utils::id("run()").to_vec().into();
+        let ((_, status, _, _), cycle) = evm
+            .call_raw_detect_delegate_cycles(sender, proxy, run.clone(), 0.into(), false)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Revert(_)));
+        assert_eq!(cycle, Some(vec![proxy, looping, proxy]));
+        assert!(evm.state().call_frames.is_none());
+
+        evm.call::<(), _, _>(sender, proxy, "setImplementation(address)", plain, 0.into(), None)
+            .unwrap();
+        let ((_, status, _, _), cycle) =
+            evm.call_raw_detect_delegate_cycles(sender, proxy, run, 0.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(cycle, None);
+    }
+
+    #[test]
+    fn recursion_depth() {
+        let compiled = COMPILED.find("Recursion").expect("could not find contract");
+
+        let mut evm = vm();
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        evm.gas_limit = 100_000;
+        let res = evm.measure_recursion_depth(Address::zero(), addr, "recurse()").unwrap();
+        assert!(res.depth > 0);
+        assert!(!res.hit_call_depth_limit);
+
+        let cfg = Config { call_stack_limit: 16, ..Config::london() };
+        let backend = new_backend(&*VICINITY, Default::default());
+        let mut evm = Executor::new_with_cheatcodes(
+            backend,
+            GAS_LIMIT,
+            &cfg,
+            &*PRECOMPILES_MAP,
+            true,
+            false,
+            false,
+        );
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let res = evm.measure_recursion_depth(Address::zero(), addr, "recurse()").unwrap();
+        assert_eq!(res, RecursionDepth { depth: 16, hit_call_depth_limit: true });
+        assert!(evm.state().call_depth.is_none());
+    }
+
+    #[test]
+    fn breakpoints() {
+        for debug in [false, true] {
+            let mut evm = if debug { vm_debug(true) } else { vm() };
+            let compiled = COMPILED.find("Breakpoints").expect("could not find contract");
+            let (addr, _, _, _) = evm
+                .deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+                .unwrap();
+            evm.call::<(), _, _>(Address::zero(), addr, "run()", (), 0.into(), compiled.abi)
+                .unwrap();
+
+            let breakpoints = &evm.state().breakpoints;
+            let names = breakpoints.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+            assert_eq!(names, ["start", "nested", "end"]);
+            let depths = breakpoints.iter().map(|(_, info)| info.depth).collect::<Vec<_>>();
+            assert_eq!(depths, [0, 1, 0]);
+            assert!(breakpoints.iter().all(|(_, info)| info.caller == addr));
+            assert!(breakpoints[0].1.gas_left > breakpoints[2].1.gas_left);
+            assert!(breakpoints.iter().all(|(_, info)| info.pc.is_some() == debug));
+        }
+    }
+
     #[test]
     fn ffi_fails_if_disabled() {
         let mut evm = vm_no_limit();
@@ -181,6 +181,7 @@ pub enum CheatOp {
     GETCODE,
     LABEL,
     ASSUME,
+    BREAKPOINT,
 }
 
 impl From<CheatOp> for OpCode {
@@ -216,6 +217,7 @@ impl CheatOp {
             CheatOp::GETCODE => "VM_GETCODE",
             CheatOp::LABEL => "VM_LABEL",
             CheatOp::ASSUME => "VM_ASSUME",
+            CheatOp::BREAKPOINT => "VM_BREAKPOINT",
         }
     }
 }
@@ -1,17 +1,24 @@
 use sputnik::{
-    backend::{Backend, Basic},
+    backend::{Apply, Backend, Basic},
     executor::stack::{MemoryStackSubstate, StackState, StackSubstateMetadata},
-    ExitError, Transfer,
+    ExitError, ExitReason, Transfer,
 };
 
-use crate::{call_tracing::CallTraceArena, sputnik::cheatcodes::debugger::DebugArena};
+use crate::{
+    call_tracing::CallTraceArena,
+    sputnik::cheatcodes::{debugger::DebugArena, overflow::ArithmeticOverflow},
+};
 
 use ethers::{
     abi::RawLog,
-    types::{H160, H256, U256},
+    types::{H160, H256, I256, U256},
 };
 
-use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+};
 
 #[derive(Clone, Default)]
 pub struct RecordAccess {
@@ -19,6 +26,43 @@ pub struct RecordAccess {
     pub writes: RefCell<BTreeMap<H160, Vec<H256>>>,
 }
 
+/// The storage slots accessed during a call, deduplicated
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageAccesses {
+    /// Every slot which was loaded. Writing a slot also loads it, to price the write.
+    pub reads: BTreeMap<H160, BTreeSet<H256>>,
+    /// Every slot which was written to
+    pub writes: BTreeMap<H160, BTreeSet<H256>>,
+}
+
+impl StorageAccesses {
+    /// Returns the slots which were read but never written to
+    pub fn read_only(&self) -> BTreeMap<H160, BTreeSet<H256>> {
+        self.reads
+            .iter()
+            .filter_map(|(address, reads)| {
+                let slots = match self.writes.get(address) {
+                    Some(writes) => reads.difference(writes).copied().collect(),
+                    None => reads.clone(),
+                };
+                (!slots.is_empty()).then(|| (*address, slots))
+            })
+            .collect()
+    }
+}
+
+impl From<RecordAccess> for StorageAccesses {
+    fn from(record: RecordAccess) -> Self {
+        let dedup = |accesses: BTreeMap<H160, Vec<H256>>| {
+            accesses
+                .into_iter()
+                .map(|(address, slots)| (address, slots.into_iter().collect()))
+                .collect()
+        };
+        Self { reads: dedup(record.reads.into_inner()), writes: dedup(record.writes.into_inner()) }
+    }
+}
+
 #[derive(Clone, Default, Debug)]
 pub struct ExpectedEmit {
     pub depth: usize,
@@ -59,6 +103,9 @@ pub struct MemoryStackStateOwned<'config, B> {
     pub traces: Vec<CallTraceArena>,
     /// Expected revert storage of bytes
     pub expected_revert: Option<Vec<u8>>,
+    /// The revert data the next top level call must revert with, `Some(None)` if any revert is
+    /// accepted
+    pub expected_call_revert: Option<Option<Vec<u8>>>,
     /// Next call's prank
     pub next_prank: Option<Prank>,
     /// StartPrank information
@@ -75,12 +122,158 @@ pub struct MemoryStackStateOwned<'config, B> {
     pub debug_enabled: bool,
     /// An arena allocator of DebugNodes for debugging purposes
     pub debug_steps: Vec<DebugArena>,
+    /// The maximum number of debug steps recorded per transaction, if set. Further steps are
+    /// executed without being recorded.
+    pub max_debug_steps: Option<usize>,
+    /// The number of debug steps recorded during the current transaction
+    pub debug_step_count: usize,
+    /// The maximum number of opcodes executed per transaction, if set. Transactions which
+    /// exceed it are halted, e.g. to bail out of loops which would only end by running out of a
+    /// very high gas limit.
+    pub max_steps: Option<usize>,
+    /// The number of opcodes executed during the current transaction, only counted if
+    /// `max_steps` is set
+    pub step_count: usize,
     /// Instruction pointers that maps an address to a mapping of pc to ic
     pub debug_instruction_pointers: Dip,
     /// Labels for an address in call traces
     pub labels: BTreeMap<H160, String>,
+    /// Precompiles whose output is overridden by a user provided function
+    pub precompile_stubs: BTreeMap<H160, PrecompileStub>,
+    /// Chronological log of all external calls, only recorded if set
+    pub calldata_log: Option<Vec<RecordedCall>>,
+    /// Whether to watch arithmetic opcodes to explain over/underflow panics. This executes
+    /// calls step by step, so it is slower.
+    pub overflow_tracing: bool,
+    /// The operation which caused the last transaction to panic, if overflow tracing is enabled
+    pub arithmetic_overflow: Option<ArithmeticOverflow>,
+    /// Named markers set by the `breakpoint` cheatcode, in the order they were hit
+    pub breakpoints: Vec<(String, BreakpointInfo)>,
+    /// Program counter of the step being executed, only tracked while debugging
+    pub current_pc: Option<usize>,
+    /// The deepest call frame entered, only tracked if set
+    pub call_depth: Option<RecursionDepth>,
+    /// The storage context and code address of each active call frame, used to detect
+    /// delegatecall cycles. Only tracked if set.
+    pub call_frames: Option<Vec<(H160, H160)>>,
+    /// The code addresses of the first delegatecall cycle detected, ending with the address
+    /// which was delegatecalled again
+    pub delegate_cycle: Option<Vec<H160>>,
+    /// Chronological log of all balance changes, only recorded if set
+    pub balance_log: Option<Vec<BalanceChange>>,
+    /// The address and revert data of every frame which reverted, in the order they exited,
+    /// only recorded if set
+    pub revert_chain: Option<Vec<(H160, Vec<u8>)>>,
+    /// The gas spent on expanding the memory of all frames, only tracked if set
+    pub memory_gas: Option<u64>,
+    /// Replaces the outcome of every finished call, if set
+    pub call_end_hook: Option<CallEndHook>,
+    /// Whether transfers of more value than the sender owns fail. If disabled, the missing
+    /// value is minted to the sender first.
+    pub balance_checks: bool,
+}
+
+/// The gas used by a call, split by what it was spent on, as returned by
+/// [`call_raw_with_gas_breakdown`](crate::sputnik::Executor::call_raw_with_gas_breakdown)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GasBreakdown {
+    /// The gas spent on executing instructions, after refunds
+    pub execution: u64,
+    /// The gas spent on expanding memory, which varies with e.g. the memory layout chosen by
+    /// the compiler
+    pub memory: u64,
+    /// The base cost of the transaction and the cost of its calldata
+    pub intrinsic: u64,
+}
+
+/// Why the balance of an account changed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BalanceChangeReason {
+    /// Value sent with a call
+    Call,
+    /// Value sent to a newly created contract
+    Create,
+    /// The remaining balance of a contract sent to the beneficiary of its `selfdestruct`
+    SelfDestruct,
+    /// The payment of the transaction's gas by its sender
+    Gas,
+    /// The part of the gas price above the base fee, paid to the block's coinbase
+    CoinbaseTip,
+}
+
+/// A balance change, as recorded in the [`balance_log`](MemoryStackStateOwned::balance_log)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub address: H160,
+    /// The amount the balance increased (positive) or decreased (negative) by
+    pub delta: I256,
+    pub reason: BalanceChangeReason,
+}
+
+/// How deep execution went, as measured by
+/// [`measure_recursion_depth`](crate::sputnik::Executor::measure_recursion_depth)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecursionDepth {
+    /// The depth of the deepest frame entered, where the top-level call has depth 0
+    pub depth: usize,
+    /// Whether a call failed because it would have exceeded the call depth limit (1024 on
+    /// mainnet), rather than running out of gas
+    pub hit_call_depth_limit: bool,
+}
+
+/// Where a `breakpoint` cheatcode was called
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreakpointInfo {
+    /// The contract which called the cheatcode
+    pub caller: H160,
+    /// The program counter of the call to the cheatcode, only known while debugging
+    pub pc: Option<usize>,
+    /// The gas left in the calling frame
+    pub gas_left: u64,
+    /// The depth of the calling frame
+    pub depth: usize,
+}
+
+/// The opcode an external call was made with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    StaticCall,
+    CallCode,
+    DelegateCall,
+}
+
+/// An external call, as recorded in the [`calldata_log`](MemoryStackStateOwned::calldata_log)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedCall {
+    pub kind: CallKind,
+    pub caller: H160,
+    /// The address whose code was executed
+    pub callee: H160,
+    /// The first 4 bytes of the calldata, if there were at least 4
+    pub selector: Option<[u8; 4]>,
+    pub calldata: Vec<u8>,
+    pub value: U256,
 }
 
+/// Counts how many times each function selector was called in a
+/// [`calldata_log`](MemoryStackStateOwned::calldata_log), across all contracts
+pub fn selector_call_counts(calls: &[RecordedCall]) -> BTreeMap<[u8; 4], usize> {
+    let mut counts = BTreeMap::new();
+    for selector in calls.iter().filter_map(|call| call.selector) {
+        *counts.entry(selector).or_default() += 1;
+    }
+    counts
+}
+
+/// A function which computes a precompile's output from its input
+pub type PrecompileStub = Rc<dyn Fn(&[u8]) -> Vec<u8>>;
+
+/// A function which maps the code address, the input and the outcome of a finished call to the
+/// outcome its caller sees, see
+/// [`set_call_end_hook`](crate::sputnik::Executor::set_call_end_hook)
+pub type CallEndHook = Rc<dyn Fn(H160, &[u8], ExitReason, Vec<u8>) -> (ExitReason, Vec<u8>)>;
+
 impl<'config, B: Backend> MemoryStackStateOwned<'config, B> {
     pub fn deposit(&mut self, address: H160, value: U256) {
         self.substate.deposit(address, value, &self.backend);
@@ -107,6 +300,21 @@ impl<'config, B: Backend> MemoryStackStateOwned<'config, B> {
         self.traces = vec![Default::default()];
         self.call_index = 0;
     }
+
+    /// Returns every account modified by the substate, along with the storage slots that were
+    /// written to.
+    pub fn touched_storage(&self) -> BTreeMap<H160, BTreeSet<H256>> {
+        let (applies, _) = self.substate.clone().deconstruct(&self.backend);
+        applies
+            .into_iter()
+            .map(|apply| match apply {
+                Apply::Modify { address, storage, .. } => {
+                    (address, storage.into_iter().map(|(slot, _)| slot).collect())
+                }
+                Apply::Delete { address } => (address, BTreeSet::new()),
+            })
+            .collect()
+    }
 }
 
 /// Debug Instruction pointers: a tuple with 2 maps, the first being for creation
@@ -135,6 +343,7 @@ impl<'config, B: Backend> MemoryStackStateOwned<'config, B> {
             trace_index: 1,
             traces: vec![Default::default()],
             expected_revert: None,
+            expected_call_revert: None,
             next_prank: None,
             prank: None,
             accesses: None,
@@ -144,8 +353,26 @@ impl<'config, B: Backend> MemoryStackStateOwned<'config, B> {
             expected_calls: Default::default(),
             debug_enabled,
             debug_steps: vec![Default::default()],
+            max_debug_steps: None,
+            debug_step_count: 0,
+            max_steps: None,
+            step_count: 0,
             debug_instruction_pointers: (BTreeMap::new(), BTreeMap::new()),
             labels: BTreeMap::new(),
+            precompile_stubs: BTreeMap::new(),
+            calldata_log: None,
+            overflow_tracing: false,
+            arithmetic_overflow: None,
+            breakpoints: Vec::new(),
+            current_pc: None,
+            call_depth: None,
+            call_frames: None,
+            delegate_cycle: None,
+            balance_log: None,
+            revert_chain: None,
+            memory_gas: None,
+            call_end_hook: None,
+            balance_checks: true,
         }
     }
 }
@@ -287,6 +514,12 @@ impl<'config, B: Backend> StackState<'config> for MemoryStackStateOwned<'config,
     }
 
     fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError> {
+        if !self.balance_checks {
+            let balance = self.basic(transfer.source).balance;
+            if balance < transfer.value {
+                self.substate.deposit(transfer.source, transfer.value - balance, &self.backend);
+            }
+        }
         self.substate.transfer(transfer, &self.backend)
     }
 
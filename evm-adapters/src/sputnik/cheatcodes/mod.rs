@@ -11,6 +11,8 @@ pub mod backend;
 
 pub mod debugger;
 
+pub mod overflow;
+
 use ethers::types::{Address, Selector, H256, U256};
 use once_cell::sync::Lazy;
 use sputnik::backend::{Backend, MemoryAccount, MemoryBackend};
@@ -32,6 +34,12 @@ pub struct Cheatcodes {
     /// The overridden block hashes, whenever `roll` gets
     /// called.
     pub block_hashes: HashMap<U256, H256>,
+    /// The overridden chain id
+    pub chain_id: Option<U256>,
+    /// Whether `blockhash` follows the EVM's rules, i.e. only returns hashes of the 256 blocks
+    /// preceding the current one and zero otherwise. Blocks without a known hash get a
+    /// deterministic pseudo-hash.
+    pub block_hash_window: bool,
 }
 
 /// Extension trait over [`Backend`] which provides additional methods for interacting with the
@@ -77,6 +85,7 @@ ethers::contract::abigen!(
             getCode(string)
             label(address,string)
             assume(bool)
+            breakpoint(string)
     ]"#,
 );
 pub use hevm_mod::{HEVMCalls, HEVM_ABI};
@@ -0,0 +1,164 @@
+//! Recovering the operands of arithmetic which made Solidity revert with `Panic(0x11)`
+use ethers::types::{H256, U256};
+use sputnik::{Opcode, Stack};
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+/// An overflowing arithmetic operation and its operands
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArithmeticOverflow {
+    pub op: ArithmeticOp,
+    pub lhs: U256,
+    pub rhs: U256,
+}
+
+impl fmt::Display for ArithmeticOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.op {
+            ArithmeticOp::Add => write!(f, "overflow in addition: {} + {}", self.lhs, self.rhs),
+            ArithmeticOp::Sub => {
+                write!(f, "underflow in subtraction: {} - {}", self.lhs, self.rhs)
+            }
+            ArithmeticOp::Mul => {
+                write!(f, "overflow in multiplication: {} * {}", self.lhs, self.rhs)
+            }
+        }
+    }
+}
+
+/// Watches the arithmetic and comparison opcodes of a single call frame, to explain the
+/// over/underflow check which made it panic.
+///
+/// solc guards every checked operation with a comparison which jumps to the panic if it
+/// holds, so the operands are taken from the last comparison before the panic. Depending on
+/// the compiler version, the comparison checks the operands before performing the operation
+/// (e.g. `if gt(x, sub(not(0), y)) { panic }` for `x + y`, or `if gt(y, x) { panic }` for
+/// `x - y`) or checks the result of the wrapping operation afterwards (e.g.
+/// `if gt(x, sum) { panic }`), so both are recognized.
+#[derive(Debug, Default)]
+pub struct OverflowTracker {
+    /// `y` of a `sub(not(0), y)`, which precedes pre-checked additions
+    add_rhs: Option<U256>,
+    /// `x` of a `div(not(0), x)`, which precedes pre-checked multiplications
+    mul_lhs: Option<U256>,
+    /// The last arithmetic operation if it wrapped around, along with its result
+    wrapped: Option<(ArithmeticOverflow, U256)>,
+    /// The overflow the last comparison checked for, if it held
+    pub candidate: Option<ArithmeticOverflow>,
+}
+
+impl OverflowTracker {
+    /// Inspects the opcode which is about to be executed
+    pub fn step(&mut self, op: Opcode, stack: &Stack) {
+        let (a, b) = match (stack.peek(0), stack.peek(1)) {
+            (Ok(a), Ok(b)) => (to_u256(a), to_u256(b)),
+            _ => return,
+        };
+        let overflow = |op, lhs, rhs| ArithmeticOverflow { op, lhs, rhs };
+
+        match op {
+            Opcode::ADD => {
+                let (sum, wrapped) = a.overflowing_add(b);
+                self.wrapped = wrapped.then(|| (overflow(ArithmeticOp::Add, a, b), sum));
+            }
+            Opcode::MUL => {
+                let (product, wrapped) = a.overflowing_mul(b);
+                self.wrapped = wrapped.then(|| (overflow(ArithmeticOp::Mul, a, b), product));
+            }
+            Opcode::SUB => {
+                let (diff, wrapped) = a.overflowing_sub(b);
+                self.wrapped = wrapped.then(|| (overflow(ArithmeticOp::Sub, a, b), diff));
+                if a == U256::MAX {
+                    self.add_rhs = Some(b);
+                }
+            }
+            Opcode::DIV if a == U256::MAX && !b.is_zero() => self.mul_lhs = Some(b),
+            // `lt(x, y)` before `x - y`
+            Opcode::LT => self.candidate = (a < b).then(|| overflow(ArithmeticOp::Sub, a, b)),
+            Opcode::GT => self.candidate = (a > b).then(|| self.checked_by_gt(a, b)),
+            // `eq(y, div(product, x))` after `x * y`
+            Opcode::EQ => {
+                self.candidate = self
+                    .wrapped
+                    .filter(|(wrapped, _)| a != b && wrapped.op == ArithmeticOp::Mul)
+                    .map(|(wrapped, _)| wrapped)
+            }
+            Opcode::SLT | Opcode::SGT => self.candidate = None,
+            _ => {}
+        }
+    }
+
+    /// Returns the overflow checked by a `gt(a, b)` which holds
+    fn checked_by_gt(&self, a: U256, b: U256) -> ArithmeticOverflow {
+        let overflow = |op, lhs, rhs| ArithmeticOverflow { op, lhs, rhs };
+        match self.wrapped {
+            // `gt(x, sum)` after `x + y`, `gt(diff, x)` after `x - y`
+            Some((wrapped, result)) if a == result || b == result => return wrapped,
+            _ => {}
+        }
+        if let Some(y) = self.add_rhs.filter(|y| b == U256::MAX - *y) {
+            overflow(ArithmeticOp::Add, a, y)
+        } else if let Some(x) = self.mul_lhs.filter(|x| b == U256::MAX / *x) {
+            overflow(ArithmeticOp::Mul, x, a)
+        } else {
+            // `gt(y, x)` before `x - y`
+            overflow(ArithmeticOp::Sub, b, a)
+        }
+    }
+}
+
+fn to_u256(value: H256) -> U256 {
+    U256::from_big_endian(value.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(values: &[u64]) -> Stack {
+        let mut stack = Stack::new(1024);
+        for value in values {
+            stack.push(H256::from_low_u64_be(*value)).unwrap();
+        }
+        stack
+    }
+
+    #[test]
+    fn only_the_last_comparison_is_a_candidate() {
+        let mut tracker = OverflowTracker::default();
+        // a loop's `lt(i, n)`
+        tracker.step(Opcode::LT, &stack(&[3, 0]));
+        assert!(tracker.candidate.is_some());
+        // `eq(value, not(0))` of the loop's increment
+        tracker.step(Opcode::EQ, &stack(&[u64::MAX, 0]));
+        assert_eq!(tracker.candidate, None);
+
+        // `gt(y, x)` before `x - y`
+        tracker.step(Opcode::GT, &stack(&[4, 10]));
+        let underflow = ArithmeticOverflow { op: ArithmeticOp::Sub, lhs: 4.into(), rhs: 10.into() };
+        assert_eq!(tracker.candidate, Some(underflow));
+    }
+
+    #[test]
+    fn recognizes_checks_of_wrapped_results() {
+        let mut tracker = OverflowTracker::default();
+        // `diff := sub(x, y)` followed by `gt(diff, x)`
+        tracker.step(Opcode::SUB, &stack(&[10, 4]));
+        let diff = U256::from(4).overflowing_sub(10.into()).0;
+        let mut stack = Stack::new(1024);
+        stack.push(H256::from_low_u64_be(4)).unwrap();
+        let mut buf = [0u8; 32];
+        diff.to_big_endian(&mut buf);
+        stack.push(H256(buf)).unwrap();
+        tracker.step(Opcode::GT, &stack);
+        let underflow = ArithmeticOverflow { op: ArithmeticOp::Sub, lhs: 4.into(), rhs: 10.into() };
+        assert_eq!(tracker.candidate, Some(underflow));
+    }
+}
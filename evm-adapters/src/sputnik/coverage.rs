@@ -0,0 +1,186 @@
+//! Attribution of executed instructions and gas to Solidity source lines
+use ethers::{solc::sourcemap::SourceMap, types::Address};
+use sputnik::Opcode;
+
+use std::collections::BTreeMap;
+
+use crate::sputnik::cheatcodes::debugger::DebugArena;
+
+/// Execution statistics of a single contract, keyed by 1-based source line
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineReport {
+    /// How many instructions mapping to each line were executed
+    pub line_coverage: BTreeMap<usize, usize>,
+    /// How much gas the instructions mapping to each line consumed
+    pub line_gas: BTreeMap<usize, u64>,
+    /// The line of the `REVERT` which ended the contract's execution, if any
+    pub revert_line: Option<usize>,
+}
+
+impl LineReport {
+    /// Correlates the debug steps recorded for `address` with the contract's source map.
+    ///
+    /// `source_index` is the index of `source` in the compiler output, steps mapping to other
+    /// sources (or to compiler generated code) are ignored. `creation` selects whether the
+    /// constructor or the runtime code's steps are analyzed, and must match the source map.
+    ///
+    /// This is best-effort: the gas of a call opcode includes the gas forwarded to the callee.
+    pub fn new(
+        arena: &DebugArena,
+        address: Address,
+        creation: bool,
+        source_map: &SourceMap,
+        source_index: u32,
+        source: &str,
+    ) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect::<Vec<_>>();
+        let line_of = |ic: usize| {
+            let element = source_map.get(ic)?;
+            if element.index != Some(source_index) {
+                return None
+            }
+            Some(match line_starts.binary_search(&element.offset) {
+                Ok(line) => line + 1,
+                Err(line) => line,
+            })
+        };
+
+        let mut report = LineReport::default();
+        for node in arena.arena.iter().filter(|node| node.address == address) {
+            if node.creation != creation {
+                continue
+            }
+            for (i, step) in node.steps.iter().enumerate() {
+                let line = match line_of(step.ic) {
+                    Some(line) => line,
+                    None => continue,
+                };
+                *report.line_coverage.entry(line).or_default() += 1;
+
+                // the gas of the last step in a node is unknown since it is only recorded before
+                // a step executes
+                if let Some(next) = node.steps.get(i + 1) {
+                    *report.line_gas.entry(line).or_default() +=
+                        next.total_gas_used.saturating_sub(step.total_gas_used);
+                }
+                if step.op.0 == Opcode::REVERT {
+                    report.revert_line = Some(line);
+                }
+            }
+        }
+        report
+    }
+}
+
+/// How often each program counter was executed, per contract and kind of code, for mapping
+/// the executed instructions to source code with a source map
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PcHits {
+    /// `(address, creation) -> (pc -> hit count)`, where `creation` distinguishes the
+    /// constructor's steps from those of the runtime code
+    pub hits: BTreeMap<(Address, bool), BTreeMap<usize, usize>>,
+}
+
+impl PcHits {
+    /// Counts the program counters of all steps recorded in the arena
+    pub fn new(arena: &DebugArena) -> Self {
+        let mut hits = Self::default();
+        hits.extend(arena);
+        hits
+    }
+
+    /// Adds the steps of another arena, e.g. of the next test
+    pub fn extend(&mut self, arena: &DebugArena) {
+        for node in &arena.arena {
+            let pcs = self.hits.entry((node.address, node.creation)).or_default();
+            for step in &node.steps {
+                *pcs.entry(step.pc).or_default() += 1;
+            }
+        }
+    }
+
+    /// Returns the hit counts of the runtime (or, if `creation` is set, the constructor) code
+    /// of `address`
+    pub fn get(&self, address: Address, creation: bool) -> Option<&BTreeMap<usize, usize>> {
+        self.hits.get(&(address, creation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sputnik::{
+            cheatcodes::debugger::{DebugNode, DebugStep, OpCode},
+            helpers::vm,
+        },
+        test_helpers::COMPILED,
+        Evm,
+    };
+    use ethers::types::U256;
+    use foundry_utils::IntoFunction;
+    use std::collections::BTreeSet;
+
+    fn step(ic: usize, op: Opcode, total_gas_used: u64) -> DebugStep {
+        DebugStep { ic, op: OpCode::from(op), total_gas_used, ..Default::default() }
+    }
+
+    #[test]
+    fn attributes_steps_to_lines() {
+        let source = "uint a;\nuint b;\nrevert();\n";
+        // one instruction per line, plus one in another source file
+        let source_map = ethers::solc::sourcemap::parse("0:7:0;8:7:0;16:9:0;0:1:1").unwrap();
+        let address = Address::from_low_u64_be(1);
+
+        let mut arena = DebugArena::default();
+        arena.push_node(
+            0,
+            DebugNode {
+                address,
+                steps: vec![
+                    step(0, Opcode::PUSH1, 0),
+                    step(1, Opcode::PUSH1, 3),
+                    step(0, Opcode::ADD, 6),
+                    step(3, Opcode::POP, 9),
+                    step(2, Opcode::REVERT, 11),
+                ],
+                ..Default::default()
+            },
+        );
+
+        let report = LineReport::new(&arena, address, false, &source_map, 0, source);
+        assert_eq!(report.line_coverage, BTreeMap::from([(1, 2), (2, 1), (3, 1)]));
+        assert_eq!(report.line_gas, BTreeMap::from([(1, 6), (2, 3)]));
+        assert_eq!(report.revert_line, Some(3));
+
+        // the creation code was never executed
+        let report = LineReport::new(&arena, address, true, &source_map, 0, source);
+        assert_eq!(report, LineReport::default());
+    }
+
+    #[test]
+    fn pc_hits_of_branches() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Branches").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let func = IntoFunction::into("branch(uint256)");
+        let mut run = |x: u64| {
+            let calldata = ethers::contract::encode_function_data(&func, U256::from(x)).unwrap();
+            let (_, arena) = evm
+                .call_raw_with_debug_arena(Address::zero(), addr, calldata, 0.into(), None)
+                .unwrap();
+            let hits = PcHits::new(&arena);
+            // the constructor did not run during the call
+            assert!(hits.get(addr, true).is_none());
+            hits.get(addr, false).unwrap().keys().copied().collect::<BTreeSet<_>>()
+        };
+        let (small, large) = (run(1), run(100));
+        assert_ne!(small, large);
+        assert!(!small.is_subset(&large));
+        assert!(!large.is_subset(&small));
+    }
+}
@@ -0,0 +1,252 @@
+//! Comparison of the committed state of executors
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use ethers::{
+    types::{Address, H256, U256},
+    utils::keccak256,
+};
+use sputnik::backend::Backend;
+
+use crate::sputnik::{
+    cheatcodes::memory_stackstate_owned::MemoryStackStateOwned, Executor, SputnikExecutor,
+};
+
+/// The differences between two executors' states, keyed by account.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub accounts: BTreeMap<Address, AccountDiff>,
+}
+
+impl StateDiff {
+    /// Returns `true` if both states were identical
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
+/// The differences of a single account, as `(left, right)` pairs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub balance: Option<(U256, U256)>,
+    pub nonce: Option<(U256, U256)>,
+    pub code: Option<(Vec<u8>, Vec<u8>)>,
+    pub storage: BTreeMap<H256, (H256, H256)>,
+}
+
+impl AccountDiff {
+    fn is_empty(&self) -> bool {
+        self.balance.is_none() &&
+            self.nonce.is_none() &&
+            self.code.is_none() &&
+            self.storage.is_empty()
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (address, diff) in &self.accounts {
+            writeln!(f, "{:?}", address)?;
+            if let Some((left, right)) = diff.balance {
+                writeln!(f, "    balance: {} -> {}", left, right)?;
+            }
+            if let Some((left, right)) = diff.nonce {
+                writeln!(f, "    nonce: {} -> {}", left, right)?;
+            }
+            if let Some((left, right)) = &diff.code {
+                writeln!(f, "    code: 0x{} -> 0x{}", hex::encode(left), hex::encode(right))?;
+            }
+            for (slot, (left, right)) in &diff.storage {
+                writeln!(f, "    storage {:?}: {:?} -> {:?}", slot, left, right)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B: Backend, E> Executor<MemoryStackStateOwned<'a, B>, E>
+where
+    E: SputnikExecutor<MemoryStackStateOwned<'a, B>>,
+{
+    /// Compares the committed state of this executor against `other`'s.
+    ///
+    /// Every account (and storage slot) modified in either executor is compared, so this is
+    /// meant for executors which started off the same backend state, e.g. to assert that a
+    /// transaction yields identical state under two configurations.
+    pub fn diff(&self, other: &Self) -> StateDiff {
+        let left = self.executor.state();
+        let right = other.executor.state();
+
+        let mut touched = left.touched_storage();
+        for (address, slots) in right.touched_storage() {
+            touched.entry(address).or_insert_with(BTreeSet::new).extend(slots);
+        }
+
+        let accounts = touched
+            .into_iter()
+            .filter_map(|(address, slots)| {
+                let (left_basic, right_basic) = (left.basic(address), right.basic(address));
+                let (left_code, right_code) = (left.code(address), right.code(address));
+                let diff = AccountDiff {
+                    balance: (left_basic.balance != right_basic.balance)
+                        .then(|| (left_basic.balance, right_basic.balance)),
+                    nonce: (left_basic.nonce != right_basic.nonce)
+                        .then(|| (left_basic.nonce, right_basic.nonce)),
+                    code: (left_code != right_code).then(|| (left_code, right_code)),
+                    storage: slots
+                        .into_iter()
+                        .filter_map(|slot| {
+                            let (l, r) =
+                                (left.storage(address, slot), right.storage(address, slot));
+                            (l != r).then(|| (slot, (l, r)))
+                        })
+                        .collect(),
+                };
+                (!diff.is_empty()).then(|| (address, diff))
+            })
+            .collect();
+
+        StateDiff { accounts }
+    }
+
+    /// Returns the storage slots whose values changed during execution, as `(old, new)` pairs
+    /// keyed by account, where the old values are the backend's.
+    ///
+    /// Like in [`diff`](Self::diff), only slots which were written to are compared, so slots
+    /// which were written back to their original value are left out.
+    pub fn storage_diff(&self) -> BTreeMap<Address, BTreeMap<H256, (H256, H256)>> {
+        let state = self.executor.state();
+        state
+            .touched_storage()
+            .into_iter()
+            .filter_map(|(address, slots)| {
+                let slots = slots
+                    .into_iter()
+                    .filter_map(|slot| {
+                        let old = state.backend.storage(address, slot);
+                        let new = state.storage(address, slot);
+                        (old != new).then(|| (slot, (old, new)))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                (!slots.is_empty()).then(|| (address, slots))
+            })
+            .collect()
+    }
+
+    /// Returns a hash over the account's balance, nonce, code hash and storage, for
+    /// checking whether an account's state is the same between runs or executors without
+    /// computing a full [`diff`](Self::diff).
+    ///
+    /// Only storage slots which were written to during execution are included, as the backend's
+    /// storage cannot be enumerated. Slots holding zero are skipped, so clearing a slot hashes
+    /// the same as never having written to it.
+    pub fn account_state_hash(&self, address: Address) -> H256 {
+        let state = self.executor.state();
+        let basic = state.basic(address);
+
+        let mut buf = [0u8; 32];
+        let mut preimage = Vec::with_capacity(96);
+        basic.balance.to_big_endian(&mut buf);
+        preimage.extend_from_slice(&buf);
+        basic.nonce.to_big_endian(&mut buf);
+        preimage.extend_from_slice(&buf);
+        preimage.extend_from_slice(&keccak256(state.code(address)));
+
+        let slots = state.touched_storage().remove(&address).unwrap_or_default();
+        for slot in slots {
+            let value = state.storage(address, slot);
+            if !value.is_zero() {
+                preimage.extend_from_slice(slot.as_bytes());
+                preimage.extend_from_slice(value.as_bytes());
+            }
+        }
+
+        keccak256(preimage).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sputnik::helpers::vm, test_helpers::COMPILED, Evm};
+
+    #[test]
+    fn diverging_storage_slot() {
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let mut left = vm();
+        let mut right = vm();
+
+        let mut addr = Address::zero();
+        for (evm, greeting) in [(&mut left, "hi"), (&mut right, "yo")] {
+            addr = evm
+                .deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+                .unwrap()
+                .0;
+            evm.call::<(), _, _>(
+                Address::zero(),
+                addr,
+                "greet(string)",
+                greeting.to_owned(),
+                0.into(),
+                None,
+            )
+            .unwrap();
+        }
+
+        assert!(left.diff(&left).is_empty());
+
+        let diff = right.diff(&left);
+        assert_eq!(diff.accounts.len(), 1);
+        let account = &diff.accounts[&addr];
+        assert!(account.balance.is_none() && account.nonce.is_none() && account.code.is_none());
+        assert_eq!(account.storage.len(), 1);
+        assert_eq!(account.storage.keys().next(), Some(&H256::zero()));
+        assert_eq!(
+            diff.to_string(),
+            format!(
+                "{:?}\n    storage 0x{}: 0x796f000000000000000000000000000000000000000000000000000000000004 -> 0x6869000000000000000000000000000000000000000000000000000000000004\n",
+                addr,
+                "0".repeat(64)
+            )
+        );
+    }
+
+    #[test]
+    fn account_state_hash_changes_on_write() {
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let mut evm = vm();
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let initial = evm.account_state_hash(addr);
+        evm.call::<String, _, _>(Address::zero(), addr, "greeting()(string)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(evm.account_state_hash(addr), initial);
+
+        evm.call::<(), _, _>(Address::zero(), addr, "gm()", (), 0.into(), None).unwrap();
+        let written = evm.account_state_hash(addr);
+        assert_ne!(written, initial);
+        assert_eq!(evm.account_state_hash(addr), written);
+    }
+
+    #[test]
+    fn storage_diff() {
+        let compiled = COMPILED.find("CallTarget").expect("could not find contract");
+        let mut evm = vm();
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        assert!(evm.storage_diff().is_empty());
+
+        evm.call::<(), _, _>(Address::zero(), addr, "set(uint256)", U256::from(1), 0.into(), None)
+            .unwrap();
+        let slot = BTreeMap::from([(H256::zero(), (H256::zero(), H256::from_low_u64_be(1)))]);
+        assert_eq!(evm.storage_diff(), BTreeMap::from([(addr, slot)]));
+
+        // writing back the original value is no change
+        evm.call::<(), _, _>(Address::zero(), addr, "set(uint256)", U256::zero(), 0.into(), None)
+            .unwrap();
+        assert!(evm.storage_diff().is_empty());
+    }
+}
@@ -1,16 +1,20 @@
-use crate::{call_tracing::CallTraceArena, Evm, FAUCET_ACCOUNT};
-use ethers::types::{Address, Bytes, U256};
+use crate::{call_tracing::CallTraceArena, DeployResult, Evm, EvmError, FAUCET_ACCOUNT};
+use ethers::types::{Address, Bloom, Bytes, Log, H256, U256};
 
 use crate::sputnik::cheatcodes::debugger::DebugArena;
 
+use serde::Deserialize;
 use sputnik::{
     backend::{Backend, MemoryAccount},
     executor::stack::{
         MemoryStackState, PrecompileSet, StackExecutor, StackState, StackSubstateMetadata,
     },
-    Config, CreateScheme, ExitReason, ExitRevert, Transfer,
+    Config, CreateScheme, ExitError, ExitReason, ExitRevert, Transfer,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    marker::PhantomData,
 };
-use std::{collections::BTreeMap, marker::PhantomData};
 
 use eyre::Result;
 
@@ -18,19 +22,775 @@ use super::SputnikExecutor;
 
 pub type MemoryState = BTreeMap<Address, MemoryAccount>;
 
+/// The maximum init code size introduced in Shanghai by EIP-3860
+pub const MAX_INITCODE_SIZE: usize = 2 * 0x6000;
+
+/// The most a nonce can be raised by at once with [`set_nonce`](Executor::set_nonce). The
+/// substate has no way to write a nonce, only to increment it, so this bounds how long setting one
+/// takes.
+pub const MAX_NONCE_INCREMENT: u64 = 1 << 20;
+
+/// The address of the canonical deterministic deployment proxy, which deploys the init code
+/// following a 32 byte salt in the calldata with CREATE2 and returns the new address
+pub const CREATE2_DEPLOYER: Address = ethers::types::H160([
+    0x4e, 0x59, 0xb4, 0x48, 0x47, 0xb3, 0x79, 0x57, 0x85, 0x88, 0x92, 0x0c, 0xa7, 0x8f, 0xbf, 0x26,
+    0xc0, 0xb4, 0x95, 0x6c,
+]);
+
+/// The runtime code of the [`CREATE2_DEPLOYER`]
+pub const CREATE2_DEPLOYER_CODE: &str = "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe03601600081602082378035828234f58015156039578182fd5b8082525050506014600cf3";
+
+/// The gas refunded to the sender of a transaction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GasRefund {
+    /// The refund accumulated during execution, e.g. by clearing storage slots
+    pub uncapped: u64,
+    /// The refund actually granted, see [`capped_refund`]
+    pub capped: u64,
+}
+
+/// Caps the refund of a transaction which used `gas_used` gas before refunds at the maximum of
+/// the hardfork: a fifth of the gas used since London (EIP-3529), half of it before
+pub fn capped_refund(config: &Config, gas_used: U256, refund: U256) -> U256 {
+    refund.min(gas_used / config.max_refund_quotient)
+}
+
+/// The outcome of a call, as in an Ethereum transaction receipt
+#[derive(Clone, Debug, PartialEq)]
+pub struct Receipt {
+    pub status: ExitReason,
+    pub gas_used: u64,
+    /// The refund which was subtracted from `gas_used`
+    pub gas_refund: GasRefund,
+    /// The logs emitted during execution, excluding those of reverted calls
+    pub logs: Vec<Log>,
+    /// The bloom filter of the logs, see [`logs_bloom`](foundry_utils::logs_bloom)
+    pub logs_bloom: Bloom,
+}
+
+impl Receipt {
+    /// Checks which of the expected logs were emitted, in any order. Each log of the receipt
+    /// is matched by at most one expected log.
+    pub fn find_logs(&self, expected: Vec<ExpectedLog>) -> LogMatches {
+        let mut matched = vec![false; self.logs.len()];
+        let (found, missing) = expected.into_iter().partition(|expected| {
+            let idx = self
+                .logs
+                .iter()
+                .enumerate()
+                .position(|(idx, log)| !matched[idx] && expected.matches(log));
+            if let Some(idx) = idx {
+                matched[idx] = true;
+            }
+            idx.is_some()
+        });
+        LogMatches { found, missing }
+    }
+}
+
+/// A log expected by [`Receipt::find_logs`]. Fields which are `None` match any value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpectedLog {
+    /// The address of the emitting contract
+    pub address: Option<Address>,
+    /// The expected topics, starting with the event signature for non-anonymous events. The
+    /// log may have more topics than listed.
+    pub topics: Vec<Option<H256>>,
+    pub data: Option<Bytes>,
+}
+
+impl ExpectedLog {
+    /// Returns whether `log` has all of the expected values
+    pub fn matches(&self, log: &Log) -> bool {
+        self.address.map_or(true, |address| address == log.address) &&
+            self.topics.len() <= log.topics.len() &&
+            self.topics
+                .iter()
+                .zip(&log.topics)
+                .all(|(expected, topic)| expected.map_or(true, |expected| expected == *topic)) &&
+            self.data.as_ref().map_or(true, |data| *data == log.data)
+    }
+}
+
+/// The outcome of [`Receipt::find_logs`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogMatches {
+    /// The expected logs which were emitted
+    pub found: Vec<ExpectedLog>,
+    /// The expected logs which were not emitted
+    pub missing: Vec<ExpectedLog>,
+}
+
+/// A transaction executed while [recording](Executor::start_recording)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedTx {
+    pub from: Address,
+    /// The called address, `None` for deployments
+    pub to: Option<Address>,
+    /// The calldata, or the init code of deployments
+    pub data: Bytes,
+    pub value: U256,
+    /// The salt of CREATE2 deployments
+    pub salt: Option<H256>,
+}
+
+/// An account of a genesis allocation, see [`load_genesis`](Executor::load_genesis). This
+/// deserializes from the entries of a `genesis.json`'s `alloc`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct GenesisAccount {
+    pub balance: U256,
+    pub nonce: U256,
+    pub code: Bytes,
+    pub storage: BTreeMap<H256, H256>,
+}
+
 // TODO: Check if we can implement this as the base layer of an ethers-provider
 // Middleware stack instead of doing RPC calls.
 /// Wrapper around Sputnik Executors which implements the [`Evm`] trait.
 pub struct Executor<S, E> {
     pub executor: E,
     pub gas_limit: u64,
+    /// Whether [`transact`](Self::transact) should reject nonces which do not match the sender's
+    /// account nonce. Disable this to replay transactions out of order.
+    pub nonce_checks: bool,
+    /// The gas limit used for the `setUp()` call. Falls back to `gas_limit` if unset, which
+    /// fixture-heavy suites may need to exceed.
+    pub setup_gas_limit: Option<u64>,
+    /// The maximum init code size of deployments, see [`MAX_INITCODE_SIZE`]. If set, init code is
+    /// also charged 2 gas per 32-byte word, as specified by EIP-3860. Unset by default since
+    /// the limit only applies from Shanghai onwards.
+    pub max_initcode_size: Option<usize>,
+    /// Accounts which are impersonated, see [`impersonate`](Self::impersonate)
+    pub impersonated: BTreeSet<Address>,
+    /// States taken by [`snapshot`](Self::snapshot), by id
+    snapshots: BTreeMap<U256, S>,
+    /// The transactions executed since recording started, if it did
+    pub(crate) recording: Option<Vec<RecordedTx>>,
+    /// The gas used by all calls and deployments, see
+    /// [`cumulative_gas_used`](Self::cumulative_gas_used)
+    pub(crate) cumulative_gas_used: u64,
+    next_snapshot_id: U256,
     marker: PhantomData<S>,
 }
 
 impl<S, E> Executor<S, E> {
     /// Instantiates the executor given a Sputnik instance.
     pub fn from_executor(executor: E, gas_limit: u64) -> Self {
-        Self { executor, gas_limit, marker: PhantomData }
+        Self {
+            executor,
+            gas_limit,
+            nonce_checks: true,
+            setup_gas_limit: None,
+            max_initcode_size: None,
+            impersonated: BTreeSet::new(),
+            snapshots: BTreeMap::new(),
+            recording: None,
+            cumulative_gas_used: 0,
+            next_snapshot_id: U256::zero(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Instantiates an executor with the settings and snapshots of `self`, around `executor`
+    pub(crate) fn clone_with(&self, executor: E) -> Self
+    where
+        S: Clone,
+    {
+        Self {
+            executor,
+            gas_limit: self.gas_limit,
+            nonce_checks: self.nonce_checks,
+            setup_gas_limit: self.setup_gas_limit,
+            max_initcode_size: self.max_initcode_size,
+            impersonated: self.impersonated.clone(),
+            snapshots: self.snapshots.clone(),
+            recording: self.recording.clone(),
+            cumulative_gas_used: self.cumulative_gas_used,
+            next_snapshot_id: self.next_snapshot_id,
+            marker: PhantomData,
+        }
+    }
+
+    /// Starts recording every call and deployment in execution order, e.g. to dump a
+    /// reproduction of a failure. Transactions whose changes are discarded later, e.g. by
+    /// [`revert`](Self::revert), stay recorded, while [`estimate_gas`](Self::estimate_gas)
+    /// runs are not recorded.
+    pub fn start_recording(&mut self) {
+        self.recording.get_or_insert_with(Vec::new);
+    }
+
+    /// Stops recording, discarding the transactions recorded so far
+    pub fn stop_recording(&mut self) {
+        self.recording = None;
+    }
+
+    /// Returns the transactions recorded so far and clears them. Recording continues if it
+    /// was started.
+    pub fn take_recording(&mut self) -> Vec<RecordedTx> {
+        self.recording.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn record(&mut self, tx: impl FnOnce() -> RecordedTx) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.push(tx());
+        }
+    }
+
+    /// Returns the total gas used by all calls and deployments since the executor was created
+    /// or [`reset_gas_counter`](Self::reset_gas_counter) was called, i.e. the sum of the gas
+    /// they reported. [`estimate_gas`](Self::estimate_gas) runs are not counted.
+    pub fn cumulative_gas_used(&self) -> u64 {
+        self.cumulative_gas_used
+    }
+
+    /// Resets the [`cumulative_gas_used`](Self::cumulative_gas_used) to zero
+    pub fn reset_gas_counter(&mut self) {
+        self.cumulative_gas_used = 0;
+    }
+
+    /// Removes all snapshots taken by [`snapshot`](Self::snapshot)
+    pub(crate) fn clear_snapshots(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Impersonates `account` until [`stop_impersonate`](Self::stop_impersonate) is called,
+    /// like Hardhat's `impersonateAccount`. Unlike a prank, this does not affect the sender of
+    /// calls, but disables the sender checks of [`transact`](Self::transact) for the account,
+    /// so transactions can be sent from e.g. contracts whose nonce is unknown to the caller.
+    ///
+    /// Impersonated accounts can also send more value than they own, the missing value is
+    /// minted to them before the call or deployment.
+    pub fn impersonate(&mut self, account: Address) {
+        self.impersonated.insert(account);
+    }
+
+    /// Stops impersonating `account`
+    pub fn stop_impersonate(&mut self, account: Address) {
+        self.impersonated.remove(&account);
+    }
+}
+
+impl<'a, S, E> Executor<S, E>
+where
+    E: SputnikExecutor<S>,
+    S: StackState<'a>,
+{
+    /// Executes a call transaction with an explicit nonce, mirroring how a mempool would
+    /// validate it: the nonce must be equal to the sender's current account nonce, otherwise a
+    /// [`EvmError::NonceTooLow`] or [`EvmError::NonceTooHigh`] is returned without executing.
+    ///
+    /// The check is skipped if `nonce_checks` is disabled or the sender is impersonated.
+    pub fn transact(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        nonce: U256,
+    ) -> std::result::Result<(Bytes, ExitReason, u64, Vec<String>), EvmError> {
+        if self.nonce_checks && !self.impersonated.contains(&from) {
+            let expected = self.executor.state().basic(from).nonce;
+            if nonce < expected {
+                return Err(EvmError::NonceTooLow { expected, provided: nonce })
+            }
+            if nonce > expected {
+                return Err(EvmError::NonceTooHigh { expected, provided: nonce })
+            }
+        }
+        Ok(self.call_raw(from, to, calldata, value, false)?)
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw) and builds the receipt of the call, with the logs
+    /// emitted during execution and their bloom filter
+    pub fn call_raw_with_receipt(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+    ) -> Result<(Bytes, Receipt)> {
+        let ((retdata, status, gas_used, _), logs, gas_refund) =
+            self.call_raw_with_logs(from, to, calldata, value, vec![]);
+        let logs_bloom = foundry_utils::logs_bloom(&logs);
+        Ok((retdata, Receipt { status, gas_used, gas_refund, logs, logs_bloom }))
+    }
+
+    /// Mints the value `from` is missing to send `value` if it is impersonated
+    fn fund_impersonated(&mut self, from: Address, value: U256) {
+        if !self.impersonated.contains(&from) {
+            return
+        }
+        let balance = self.executor.state().basic(from).balance;
+        if balance < value {
+            let funding =
+                Transfer { source: *FAUCET_ACCOUNT, target: from, value: value - balance };
+            self.executor.state_mut().transfer(funding).expect("could not transfer funds");
+        }
+    }
+
+    fn call_raw_with_logs(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        access_list: Vec<(Address, Vec<H256>)>,
+    ) -> ((Bytes, ExitReason, u64, Vec<String>), Vec<Log>, GasRefund) {
+        self.record(|| RecordedTx {
+            from,
+            to: Some(to),
+            data: calldata.clone(),
+            value,
+            salt: None,
+        });
+        self.fund_impersonated(from, value);
+        let gas_used_before = self.executor.gas_used();
+        let refunded_gas_before = self.executor.gas_refund();
+
+        let config = self.executor.config();
+        let access_list_cost = access_list.iter().fold(0, |cost, (_, keys)| {
+            cost + config.gas_access_list_address +
+                keys.len() as u64 * config.gas_access_list_storage_key
+        });
+        let (status, retdata) = self.executor.transact_call(
+            from,
+            to,
+            value,
+            calldata.to_vec(),
+            self.gas_limit,
+            access_list,
+        );
+
+        tracing::trace!(logs_before = ?self.executor.logs());
+
+        let refunded_gas = self.executor.gas_refund().saturating_sub(refunded_gas_before);
+        let gas_used = self.executor.gas_used().saturating_sub(gas_used_before);
+        let capped = capped_refund(self.executor.config(), gas_used, refunded_gas);
+        let gas_refund = GasRefund { uncapped: refunded_gas.as_u64(), capped: capped.as_u64() };
+        // remove base, calldata and access list costs
+        let gas =
+            foundry_utils::remove_extra_costs(gas_used.saturating_sub(capped), calldata.as_ref())
+                .saturating_sub(access_list_cost.into());
+        self.cumulative_gas_used += gas.as_u64();
+
+        // get the logs
+        let logs = self.executor.logs();
+        let emitted_logs = self.executor.emitted_logs();
+        tracing::trace!(logs_after = ?self.executor.logs());
+        // clear them
+        self.executor.clear_logs();
+
+        ((retdata.into(), status, gas.as_u64(), logs), emitted_logs, gas_refund)
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw) with an EIP-2930 access list of `(address, slots)`
+    /// pairs, which are warm from the start of the call. The access list's own cost is not
+    /// included in the returned gas, like the other intrinsic costs.
+    pub fn call_raw_with_access_list(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        access_list: Vec<(Address, Vec<U256>)>,
+    ) -> Result<(Bytes, ExitReason, u64, Vec<String>)> {
+        let access_list = access_list
+            .into_iter()
+            .map(|(address, slots)| {
+                let slots = slots
+                    .into_iter()
+                    .map(|slot| {
+                        let mut buf = [0u8; 32];
+                        slot.to_big_endian(&mut buf);
+                        H256(buf)
+                    })
+                    .collect();
+                (address, slots)
+            })
+            .collect();
+        Ok(self.call_raw_with_logs(from, to, calldata, value, access_list).0)
+    }
+
+    /// Sends `value` to `to` with no calldata and only the call stipend (2300 gas) available
+    /// for execution, like Solidity's `transfer` and `send` do. This allows checking whether a
+    /// contract's `receive()` function can accept ether that way.
+    ///
+    /// The transaction's gas limit is set to the intrinsic cost of a call plus the stipend.
+    pub fn call_with_gas_stipend(
+        &mut self,
+        from: Address,
+        to: Address,
+        value: U256,
+    ) -> Result<(Bytes, ExitReason, u64, Vec<String>)> {
+        let config = self.executor.config();
+        let gas_limit = config.gas_transaction_call + config.call_stipend;
+        self.call_raw_with_gas(from, to, Bytes::default(), value, gas_limit)
+    }
+
+    /// Sets the code of the [`CREATE2_DEPLOYER`], which many deployment scripts expect to
+    /// exist, as it does on most chains
+    pub fn install_create2_deployer(&mut self) {
+        let code = hex::decode(CREATE2_DEPLOYER_CODE).expect("valid hex");
+        self.initialize_contracts([(CREATE2_DEPLOYER, code.into())]);
+    }
+
+    /// Seeds the balance, nonce, code and storage of every account of the genesis `alloc`,
+    /// without executing any transactions.
+    ///
+    /// Errors without modifying any account if an allocated nonce could not be
+    /// [set](Self::set_nonce), e.g. because it is lower than the account's current nonce.
+    pub fn load_genesis(&mut self, alloc: HashMap<Address, GenesisAccount>) -> Result<()> {
+        let mut increments = HashMap::with_capacity(alloc.len());
+        for (address, account) in &alloc {
+            eyre::ensure!(
+                account.nonce <= u64::MAX.into(),
+                "the nonce {} of {:?} does not fit in 64 bits",
+                account.nonce,
+                address
+            );
+            increments.insert(*address, self.nonce_increment(*address, account.nonce.as_u64())?);
+        }
+
+        for (address, account) in alloc {
+            let state = self.executor.state_mut();
+            state.reset_balance(address);
+            for _ in 0..increments[&address] {
+                state.inc_nonce(address);
+            }
+            if !account.code.is_empty() {
+                state.set_code(address, account.code.to_vec());
+            }
+            for (slot, value) in account.storage {
+                state.set_storage(address, slot, value);
+            }
+            self.set_balance(address, account.balance);
+        }
+        Ok(())
+    }
+
+    /// Returns the smallest transaction gas limit, including the intrinsic cost, with which the
+    /// call succeeds. This can be more than the gas the call uses, since refunds are only
+    /// granted at the end and calls only forward 63/64 of the remaining gas.
+    ///
+    /// The limit is binary searched between the gas used and the executor's `gas_limit`, and
+    /// the state is restored after every attempt. Errors if the call fails even with the
+    /// executor's `gas_limit`.
+    pub fn estimate_gas(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+    ) -> Result<u64>
+    where
+        S: Clone,
+    {
+        let recording = self.recording.take();
+        let cumulative_gas_used = self.cumulative_gas_used;
+        let res = self.estimate_gas_inner(from, to, calldata, value);
+        self.recording = recording;
+        self.cumulative_gas_used = cumulative_gas_used;
+        res
+    }
+
+    fn estimate_gas_inner(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+    ) -> Result<u64>
+    where
+        S: Clone,
+    {
+        let state = self.executor.state().clone();
+        let run = |evm: &mut Self, gas_limit: u64| {
+            let res = evm.call_raw_with_gas(from, to, calldata.clone(), value, gas_limit);
+            *evm.executor.state_mut() = state.clone();
+            res.map(|(retdata, status, gas, _)| (retdata, status, gas))
+        };
+
+        let max_gas_limit = self.gas_limit;
+        let (retdata, status, gas) = run(self, max_gas_limit)?;
+        if !matches!(status, ExitReason::Succeed(_)) {
+            let reason = match status {
+                ExitReason::Revert(_) => foundry_utils::decode_revert(retdata.as_ref(), None)
+                    .unwrap_or_else(|_| format!("0x{}", hex::encode(&retdata))),
+                status => Self::describe_failure(&status),
+            };
+            eyre::bail!("call fails with the gas limit of {}: {}", max_gas_limit, reason)
+        }
+
+        // the gas used excludes the refund, so the call always fails with less
+        let mut failing = (gas + foundry_utils::intrinsic_gas(calldata.as_ref())).saturating_sub(1);
+        let mut succeeding = max_gas_limit;
+        while succeeding - failing > 1 {
+            let gas_limit = failing + (succeeding - failing) / 2;
+            let (_, status, _) = run(self, gas_limit)?;
+            if matches!(status, ExitReason::Succeed(_)) {
+                succeeding = gas_limit;
+            } else {
+                failing = gas_limit;
+            }
+        }
+        Ok(succeeding)
+    }
+
+    /// Executes a [`call_raw`](Evm::call_raw) with a transaction gas limit of `gas_limit`
+    /// instead of the executor's `gas_limit`, e.g. to check how a call behaves when it runs
+    /// out of gas. The gas limit includes the intrinsic cost of the transaction.
+    pub fn call_raw_with_gas(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        gas_limit: u64,
+    ) -> Result<(Bytes, ExitReason, u64, Vec<String>)> {
+        let prev_gas_limit = std::mem::replace(&mut self.gas_limit, gas_limit);
+        let res = self.call_raw(from, to, calldata, value, false);
+        self.gas_limit = prev_gas_limit;
+        res
+    }
+
+    /// Calls [`set_balance`](Evm::set_balance) for each `(address, balance)` pair, in order
+    pub fn set_balances(&mut self, balances: &[(Address, U256)]) {
+        for (address, balance) in balances {
+            self.set_balance(*address, *balance);
+        }
+    }
+
+    /// Returns the balances of the given accounts, in the same order
+    pub fn get_balances(&self, addresses: &[Address]) -> Vec<U256> {
+        let state = self.executor.state();
+        addresses.iter().map(|address| state.basic(*address).balance).collect()
+    }
+
+    /// Sets the nonce of `address`, which determines the addresses of the contracts it deploys.
+    ///
+    /// Errors if `nonce` is lower than the account's current nonce, since nonces can only be
+    /// incremented, or if it is more than [`MAX_NONCE_INCREMENT`] above it.
+    pub fn set_nonce(&mut self, address: Address, nonce: u64) -> Result<()> {
+        let increment = self.nonce_increment(address, nonce)?;
+        let state = self.executor.state_mut();
+        for _ in 0..increment {
+            state.inc_nonce(address);
+        }
+        Ok(())
+    }
+
+    /// Returns by how much the nonce of `address` has to be incremented to reach `nonce`, see
+    /// [`set_nonce`](Self::set_nonce)
+    fn nonce_increment(&self, address: Address, nonce: u64) -> Result<u64> {
+        let current = self.executor.state().basic(address).nonce;
+        eyre::ensure!(
+            current <= nonce.into(),
+            "cannot decrease the nonce of {:?} from {} to {}",
+            address,
+            current,
+            nonce
+        );
+        let increment = nonce - current.as_u64();
+        eyre::ensure!(
+            increment <= MAX_NONCE_INCREMENT,
+            "cannot raise the nonce of {:?} from {} to {}, by more than {} at once",
+            address,
+            current,
+            nonce,
+            MAX_NONCE_INCREMENT
+        );
+        Ok(increment)
+    }
+
+    /// Takes a snapshot of the whole state, which can be restored with
+    /// [`revert`](Self::revert), and returns its id
+    pub fn snapshot(&mut self) -> U256
+    where
+        S: Clone,
+    {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += U256::one();
+        self.snapshots.insert(id, self.executor.state().clone());
+        id
+    }
+
+    /// Restores the state of the snapshot `id`, discarding all changes made since, including
+    /// those made by e.g. [`set_balance`](Evm::set_balance). Like Hardhat's `evm_revert`, the
+    /// snapshot and all snapshots taken after it are removed.
+    ///
+    /// Returns whether the snapshot existed.
+    pub fn revert(&mut self, id: U256) -> bool {
+        let state = match self.snapshots.remove(&id) {
+            Some(state) => state,
+            None => return false,
+        };
+        self.snapshots.retain(|snapshot, _| *snapshot < id);
+        *self.executor.state_mut() = state;
+        true
+    }
+
+    /// Like [`deploy`](Evm::deploy), but deploys with CREATE2, so the address only depends on
+    /// the sender, the salt and the init code
+    pub fn deploy_create2(
+        &mut self,
+        from: Address,
+        code: Bytes,
+        salt: U256,
+        value: U256,
+    ) -> Result<(Address, ExitReason, u64, Vec<String>)> {
+        let mut buf = [0u8; 32];
+        salt.to_big_endian(&mut buf);
+        let DeployResult { address, status, gas_used, logs } =
+            self.try_deploy_with_salt(from, code, value, Some(H256(buf)))?;
+        Ok((address, status, gas_used, logs))
+    }
+
+    /// Like [`deploy`](Evm::deploy), but also returns the runtime code of the deployed contract,
+    /// e.g. to compare it against the expected artifact
+    pub fn deploy_with_code(
+        &mut self,
+        from: Address,
+        calldata: Bytes,
+        value: U256,
+    ) -> Result<(Address, ExitReason, u64, Vec<String>, Bytes)> {
+        let DeployResult { address, status, gas_used, logs } =
+            self.try_deploy(from, calldata, value)?;
+        Ok((address, status, gas_used, logs, self.code(address).into()))
+    }
+
+    /// Deploys with CREATE2 if a salt is given, with CREATE otherwise
+    fn try_deploy_with_salt(
+        &mut self,
+        from: Address,
+        calldata: Bytes,
+        value: U256,
+        salt: Option<H256>,
+    ) -> std::result::Result<DeployResult<ExitReason>, EvmError> {
+        let mut initcode_cost = 0;
+        if let Some(limit) = self.max_initcode_size {
+            if calldata.len() > limit {
+                return Err(EvmError::InitCodeSizeExceeded { size: calldata.len(), limit })
+            }
+            initcode_cost = 2 * ((calldata.len() as u64 + 31) / 32);
+        }
+
+        self.record(|| RecordedTx { from, to: None, data: calldata.clone(), value, salt });
+        self.fund_impersonated(from, value);
+        let gas_used_before = self.executor.gas_used();
+        let refunded_gas_before = self.executor.gas_refund();
+
+        // The account's created contract address is pre-computed by using the account's nonce
+        // before it executes the contract deployment transaction.
+        let scheme = match salt {
+            Some(salt) => {
+                let code_hash = H256::from(ethers::utils::keccak256(&calldata));
+                CreateScheme::Create2 { caller: from, code_hash, salt }
+            }
+            None => CreateScheme::Legacy { caller: from },
+        };
+        let address = self.executor.create_address(scheme);
+        // sputnik does not meter init code, so its cost is deducted from the gas limit upfront
+        let gas_limit = self.gas_limit.saturating_sub(initcode_cost);
+        let (status, retdata) = match salt {
+            Some(salt) => self.executor.transact_create2(
+                from,
+                value,
+                calldata.to_vec(),
+                salt,
+                gas_limit,
+                vec![],
+            ),
+            None => {
+                self.executor.transact_create(from, value, calldata.to_vec(), gas_limit, vec![])
+            }
+        };
+
+        // get the deployment logs
+        let logs = self.executor.logs();
+        // and clear them
+        self.executor.clear_logs();
+
+        let refunded_gas = self.executor.gas_refund().saturating_sub(refunded_gas_before);
+        let gas_used = self.executor.gas_used().saturating_sub(gas_used_before);
+        let refunded_gas = capped_refund(self.executor.config(), gas_used, refunded_gas);
+        // we dont remove call data costs here because its highly relevant to users
+        let gas =
+            gas_used.saturating_sub(refunded_gas).saturating_sub(21000.into()) + initcode_cost;
+        let gas_used = gas.as_u64();
+        self.cumulative_gas_used += gas_used;
+
+        if let Some(limit) = self.executor.step_limit_exceeded() {
+            Err(EvmError::StepLimitExceeded { limit, logs })
+        } else if Self::is_fail(&status) {
+            tracing::trace!(?status, "failed");
+            let reason = foundry_utils::decode_revert(&retdata, None)
+                .unwrap_or_else(|_| Self::describe_failure(&status));
+            let revert_data = Some(retdata.into());
+            Err(EvmError::Execution { reason, gas_used, logs, revert_data })
+        } else if self.code(address).is_empty() {
+            tracing::trace!(?status, ?address, "no code deployed");
+            let reason = "constructor returned no code".to_string();
+            Err(EvmError::Execution { reason, gas_used, logs, revert_data: None })
+        } else {
+            tracing::trace!(?status, ?address, ?gas, "success");
+            Ok(DeployResult { address, status, gas_used, logs })
+        }
+    }
+
+    /// Returns the nonce of `address`, saturated at `u64::MAX` since accounts of e.g. a forked
+    /// backend may have larger nonces
+    pub fn get_nonce(&self, address: Address) -> u64 {
+        self.executor.state().basic(address).nonce.min(u64::MAX.into()).as_u64()
+    }
+
+    /// Places `code` at `address`, without running any constructor. Empty code turns the
+    /// account back into an EOA.
+    pub fn set_code(&mut self, address: Address, code: Bytes) {
+        self.executor.state_mut().set_code(address, code.to_vec());
+    }
+
+    /// Whether `address` is empty as defined by EIP-161, i.e. has no code, a nonce of zero and
+    /// no balance
+    pub fn is_empty_account(&self, address: Address) -> bool {
+        self.executor.state().is_empty(address)
+    }
+
+    /// Returns the value of the storage `slot` of `address`
+    pub fn read_storage(&self, address: Address, slot: U256) -> U256 {
+        let mut buf = [0u8; 32];
+        slot.to_big_endian(&mut buf);
+        U256::from_big_endian(self.executor.state().storage(address, H256(buf)).as_bytes())
+    }
+
+    /// Sets the storage `slot` of `address` to `value`, without executing a transaction
+    pub fn write_storage(&mut self, address: Address, slot: U256, value: U256) {
+        let (mut slot_buf, mut value_buf) = ([0u8; 32], [0u8; 32]);
+        slot.to_big_endian(&mut slot_buf);
+        value.to_big_endian(&mut value_buf);
+        self.executor.state_mut().set_storage(address, H256(slot_buf), H256(value_buf));
+    }
+
+    /// Checks that the storage slots of `address` hold the expected `(slot, value)` pairs,
+    /// returning an error which lists every mismatching slot otherwise
+    pub fn assert_storage_layout(&self, address: Address, expected: &[(U256, U256)]) -> Result<()> {
+        let mismatches = expected
+            .iter()
+            .filter_map(|(slot, expected)| {
+                let actual = self.read_storage(address, *slot);
+                (actual != *expected).then(|| {
+                    format!("slot {:#x}: expected {:#x}, got {:#x}", slot, expected, actual)
+                })
+            })
+            .collect::<Vec<_>>();
+        if !mismatches.is_empty() {
+            eyre::bail!(
+                "storage of {:?} does not match the expected layout:\n{}",
+                address,
+                mismatches.join("\n")
+            )
+        }
+        Ok(())
     }
 }
 
@@ -48,7 +808,7 @@ impl<'a, 'b, B: Backend, P: PrecompileSet>
         // setup executor
         let executor = StackExecutor::new_with_precompiles(state, config, precompiles);
 
-        Self { executor, gas_limit, marker: PhantomData }
+        Self::from_executor(executor, gas_limit)
     }
 }
 
@@ -80,6 +840,21 @@ where
         !Self::is_success(reason)
     }
 
+    fn describe_failure(reason: &Self::ReturnReason) -> String {
+        match reason {
+            ExitReason::Error(ExitError::OutOfGas) => "out of gas".to_string(),
+            ExitReason::Error(ExitError::DesignatedInvalid | ExitError::InvalidCode { .. }) => {
+                "invalid opcode".to_string()
+            }
+            ExitReason::Error(ExitError::StackUnderflow) => "stack underflow".to_string(),
+            ExitReason::Error(ExitError::StackOverflow) => "stack overflow".to_string(),
+            ExitReason::Error(ExitError::CallTooDeep) => "call depth exceeded".to_string(),
+            ExitReason::Error(ExitError::InvalidJump) => "invalid jump destination".to_string(),
+            ExitReason::Error(ExitError::OutOfFund) => "insufficient balance".to_string(),
+            reason => format!("{:?}", reason),
+        }
+    }
+
     fn reset(&mut self, state: S) {
         let mut _state = self.executor.state_mut();
         *_state = state;
@@ -135,42 +910,50 @@ where
         self.executor.all_logs()
     }
 
+    fn arithmetic_overflow(&self) -> Option<String> {
+        self.executor.arithmetic_overflow()
+    }
+
+    /// Runs `setUp()` with the configured `setup_gas_limit`, reporting out of gas errors
+    /// as [`EvmError::SetupOutOfGas`]
+    fn setup(&mut self, address: Address) -> Result<(ExitReason, Vec<String>)> {
+        let span = tracing::trace_span!("setup", ?address);
+        let _enter = span.enter();
+
+        let gas_limit = self.setup_gas_limit.unwrap_or(self.gas_limit);
+        let prev_gas_limit = std::mem::replace(&mut self.gas_limit, gas_limit);
+        let res = self.call_raw(
+            Address::zero(),
+            address,
+            ethers::utils::id("setUp()").to_vec().into(),
+            0.into(),
+            false,
+        );
+        self.gas_limit = prev_gas_limit;
+
+        let (retdata, status, gas_used, logs) = res?;
+        match status {
+            ExitReason::Error(ExitError::OutOfGas) => {
+                Err(EvmError::SetupOutOfGas { gas_limit, logs }.into())
+            }
+            status if Self::is_fail(&status) => {
+                let reason = foundry_utils::decode_revert(retdata.as_ref(), None)
+                    .unwrap_or_else(|_| Self::describe_failure(&status));
+                Err(EvmError::Execution { reason, gas_used, logs, revert_data: Some(retdata) }
+                    .into())
+            }
+            status => Ok((status, logs)),
+        }
+    }
+
     /// Deploys the provided contract bytecode
-    fn deploy(
+    fn try_deploy(
         &mut self,
         from: Address,
         calldata: Bytes,
         value: U256,
-    ) -> Result<(Address, ExitReason, u64, Vec<String>)> {
-        let gas_used_before = self.executor.gas_used();
-        let refunded_gas_before = self.executor.gas_refund();
-
-        // The account's created contract address is pre-computed by using the account's nonce
-        // before it executes the contract deployment transaction.
-        let address = self.executor.create_address(CreateScheme::Legacy { caller: from });
-        let status =
-            self.executor.transact_create(from, value, calldata.to_vec(), self.gas_limit, vec![]);
-
-        // get the deployment logs
-        let logs = self.executor.logs();
-        // and clear them
-        self.executor.clear_logs();
-
-        let refunded_gas = self.executor.gas_refund().saturating_sub(refunded_gas_before);
-        let gas_used_after = self.executor.gas_used();
-        // we dont remove call data costs here because its highly relevant to users
-        let gas = gas_used_after
-            .saturating_sub(gas_used_before)
-            .saturating_sub(refunded_gas)
-            .saturating_sub(21000.into());
-
-        if Self::is_fail(&status) {
-            tracing::trace!(?status, "failed");
-            Err(eyre::eyre!("deployment reverted, reason: {:?}", status))
-        } else {
-            tracing::trace!(?status, ?address, ?gas, "success");
-            Ok((address, status, gas.as_u64(), logs))
-        }
+    ) -> std::result::Result<DeployResult<ExitReason>, EvmError> {
+        self.try_deploy_with_salt(from, calldata, value, None)
     }
 
     /// Runs the selected function
@@ -182,37 +965,19 @@ where
         value: U256,
         _is_static: bool,
     ) -> Result<(Bytes, ExitReason, u64, Vec<String>)> {
-        let gas_used_before = self.executor.gas_used();
-        let refunded_gas_before = self.executor.gas_refund();
-
-        let (status, retdata) =
-            self.executor.transact_call(from, to, value, calldata.to_vec(), self.gas_limit, vec![]);
-
-        tracing::trace!(logs_before = ?self.executor.logs());
+        let res = self.call_raw_with_logs(from, to, calldata, value, vec![]).0;
+        if let Some(limit) = self.executor.step_limit_exceeded() {
+            return Err(EvmError::StepLimitExceeded { limit, logs: res.3 }.into())
+        }
+        Ok(res)
+    }
+}
 
-        let refunded_gas = self.executor.gas_refund().saturating_sub(refunded_gas_before);
-        let gas_used_after = self.executor.gas_used();
-        // remove base and calldata costs
-        let gas = foundry_utils::remove_extra_costs(
-            gas_used_after.saturating_sub(gas_used_before).saturating_sub(refunded_gas),
-            calldata.as_ref(),
-        );
-
-        // get the logs
-        let logs = self.executor.logs();
-        tracing::trace!(logs_after = ?self.executor.logs());
-        // clear them
-        self.executor.clear_logs();
-
-        Ok((retdata.into(), status, gas.as_u64(), logs))
-    }
-}
-
-#[cfg(any(test, feature = "sputnik-helpers"))]
-pub mod helpers {
-    use super::*;
-    use ethers::types::H160;
-    use sputnik::backend::{MemoryBackend, MemoryVicinity};
+#[cfg(any(test, feature = "sputnik-helpers"))]
+pub mod helpers {
+    use super::*;
+    use ethers::types::H160;
+    use sputnik::backend::{MemoryBackend, MemoryVicinity};
 
     use crate::{
         fuzz::FuzzedExecutor,
@@ -361,11 +1126,16 @@ pub mod helpers {
 mod tests {
     use super::*;
     use crate::{
-        sputnik::helpers::vm,
+        sputnik::{
+            helpers::{new_backend, vm, TestSputnikVM, GAS_LIMIT, VICINITY},
+            PRECOMPILES_MAP,
+        },
         test_helpers::{can_call_vm_directly, solidity_unit_test, COMPILED},
+        SetupResult,
     };
     use ethers::utils::id;
-    use sputnik::{ExitReason, ExitRevert, ExitSucceed};
+    use foundry_utils::IntoFunction;
+    use sputnik::{backend::MemoryBackend, ExitReason, ExitRevert, ExitSucceed};
 
     // can bubble up sputnik errors
     #[test]
@@ -383,7 +1153,1034 @@ mod tests {
             crate::EvmError::Execution { reason, gas_used, .. } => (reason, gas_used),
             _ => panic!("unexpected error variant"),
         };
-        assert_eq!(reason, "Error(OutOfGas)");
+        assert_eq!(reason, "out of gas");
+    }
+
+    #[test]
+    fn describes_invalid_opcode() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("InvalidOpcode").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let err =
+            evm.call::<(), _, _>(Address::zero(), addr, "fail()", (), 0.into(), None).unwrap_err();
+        assert!(
+            matches!(err, EvmError::Execution { ref reason, .. } if reason == "invalid opcode")
+        );
+    }
+
+    #[test]
+    fn call_raw_with_access_list() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("CallTarget").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let calldata: Bytes = id("x()").to_vec().into();
+        let (_, _, cold, _) =
+            evm.call_raw(Address::zero(), addr, calldata.clone(), 0.into(), true).unwrap();
+        let access_list = vec![(addr, vec![U256::zero()])];
+        let (_, status, warm, _) = evm
+            .call_raw_with_access_list(Address::zero(), addr, calldata, 0.into(), access_list)
+            .unwrap();
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        // a warm SLOAD costs 100 instead of 2100 gas
+        assert_eq!(cold - warm, 2000);
+    }
+
+    #[test]
+    fn receive_with_gas_stipend() {
+        let mut evm = vm();
+        let from = Address::random();
+        evm.set_balance(from, 10.into());
+
+        let compiled = COMPILED.find("CheapReceiver").expect("could not find contract");
+        let (cheap, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let compiled = COMPILED.find("ExpensiveReceiver").expect("could not find contract");
+        let (expensive, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let (_, status, _, _) = evm.call_with_gas_stipend(from, cheap, 1.into()).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(evm.state().basic(cheap).balance, 1.into());
+
+        let gas_limit = evm.gas_limit;
+        let (_, status, _, _) = evm.call_with_gas_stipend(from, expensive, 1.into()).unwrap();
+        assert!(matches!(status, ExitReason::Error(_)));
+        assert_eq!(evm.state().basic(expensive).balance, 0.into());
+        assert_eq!(evm.gas_limit, gas_limit);
+
+        // a regular call has enough gas to write to storage
+        let (_, status, _, _) =
+            evm.call_raw(from, expensive, Bytes::default(), 1.into(), false).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+    }
+
+    #[test]
+    fn call_full_returns_raw_output() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        evm.call::<(), _, _>(Address::zero(), addr, "gm()", (), 0.into(), None).unwrap();
+
+        let func = "greeting()(string)";
+        let ((greeting, _, _, _), retdata) =
+            evm.call_full::<String, _, _>(Address::zero(), addr, func, (), 0.into(), None).unwrap();
+        assert_eq!(greeting, "gm");
+        assert_eq!(retdata.to_vec(), ethers::abi::encode(&[ethers::abi::Token::String(greeting)]));
+    }
+
+    #[test]
+    fn call_raw_with_receipt() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("DebugLogs").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let calldata = id("test_log()").to_vec().into();
+        let (_, receipt) =
+            evm.call_raw_with_receipt(Address::zero(), addr, calldata, 0.into()).unwrap();
+        assert!(matches!(receipt.status, ExitReason::Succeed(_)));
+        assert!(receipt.logs.iter().all(|log| log.address == addr));
+        assert_eq!(receipt.logs[0].topics, vec![ethers::utils::keccak256("log(string)").into()]);
+        assert_ne!(receipt.logs_bloom, Bloom::zero());
+        assert_eq!(receipt.logs_bloom, foundry_utils::logs_bloom(&receipt.logs));
+        // the logs are cleared after each call
+        assert!(evm.executor.emitted_logs().is_empty());
+    }
+
+    #[test]
+    fn nested_logs() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("NestedEmitter").expect("could not find contract");
+        let (parent, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let (child, _, _, _) = evm
+            .call::<Address, _, _>(Address::zero(), parent, "child()(address)", (), 0.into(), None)
+            .unwrap();
+
+        let (_, receipt) = evm
+            .call_raw_with_receipt(Address::zero(), parent, id("run()").to_vec().into(), 0.into())
+            .unwrap();
+        // the log of the reverted call is dropped
+        let logs = receipt
+            .logs
+            .iter()
+            .map(|log| (log.address, U256::from_big_endian(&log.data), log.transaction_log_index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            logs,
+            vec![
+                (parent, 1.into(), Some(0.into())),
+                (child, 2.into(), Some(1.into())),
+                (parent, 4.into(), Some(2.into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_logs() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Emitter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let sender = Address::random();
+        let func = IntoFunction::into("emitBoth(uint256)");
+        let calldata = ethers::contract::encode_function_data(&func, U256::from(5)).unwrap();
+        let (_, receipt) = evm.call_raw_with_receipt(sender, addr, calldata, 0.into()).unwrap();
+        assert_eq!(receipt.logs.len(), 2);
+
+        let deposit = H256::from(ethers::utils::keccak256("Deposit(address,uint256)"));
+        let withdrawal = H256::from(ethers::utils::keccak256("Withdrawal(address,uint256)"));
+        // only the indexed sender is checked, not the amount
+        let deposit_by_sender = ExpectedLog {
+            address: Some(addr),
+            topics: vec![Some(deposit), Some(sender.into())],
+            data: None,
+        };
+        let withdrawal_by_other = ExpectedLog {
+            topics: vec![Some(withdrawal), Some(Address::random().into())],
+            ..Default::default()
+        };
+        let deposit_by_other_emitter = ExpectedLog {
+            address: Some(Address::random()),
+            topics: vec![Some(deposit)],
+            data: None,
+        };
+        let matches = receipt.find_logs(vec![
+            deposit_by_sender.clone(),
+            withdrawal_by_other.clone(),
+            deposit_by_other_emitter.clone(),
+        ]);
+        assert_eq!(matches.found, vec![deposit_by_sender]);
+        assert_eq!(matches.missing, vec![withdrawal_by_other, deposit_by_other_emitter]);
+
+        // the data is compared if set
+        let data = ethers::abi::encode(&[ethers::abi::Token::Uint(10.into())]);
+        let withdrawal_of = |data: Vec<u8>| ExpectedLog {
+            topics: vec![Some(withdrawal)],
+            data: Some(data.into()),
+            ..Default::default()
+        };
+        let matches = receipt.find_logs(vec![withdrawal_of(data), withdrawal_of(vec![])]);
+        assert_eq!(matches.found.len(), 1);
+        assert_eq!(matches.missing.len(), 1);
+    }
+
+    #[test]
+    fn decode_logs() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Emitter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let sender = Address::random();
+        let func = IntoFunction::into("emitBoth(uint256)");
+        let calldata = ethers::contract::encode_function_data(&func, U256::from(5)).unwrap();
+        let (_, receipt) = evm.call_raw_with_receipt(sender, addr, calldata, 0.into()).unwrap();
+        let mut logs = receipt
+            .logs
+            .into_iter()
+            .map(|log| ethers::abi::RawLog { topics: log.topics, data: log.data.to_vec() })
+            .collect::<Vec<_>>();
+        // a log of an unknown event
+        logs.push(ethers::abi::RawLog { topics: vec![H256::random()], data: vec![] });
+
+        let abi = compiled.abi.unwrap();
+        let decoded = foundry_utils::decode_logs(&logs, abi);
+        let (address, uint) = (ethers::abi::Token::Address, ethers::abi::Token::Uint);
+        assert_eq!(
+            decoded,
+            vec![
+                ("Deposit".to_string(), vec![address(sender), uint(5.into())]),
+                ("Withdrawal".to_string(), vec![address(sender), uint(10.into())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn estimate_gas() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("HeavySetup").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let calldata: Bytes = id("setUp()").to_vec().into();
+        let estimate = evm.estimate_gas(Address::zero(), addr, calldata.clone(), 0.into()).unwrap();
+        // the state is left untouched
+        let (_, status, _, _) = evm
+            .call::<(), _, _>(Address::zero(), addr, "testValues()", (), 0.into(), compiled.abi)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Revert(_)));
+
+        let snapshot = evm.snapshot();
+        let (_, status, _, _) = evm
+            .call_raw_with_gas(Address::zero(), addr, calldata.clone(), 0.into(), estimate - 1)
+            .unwrap();
+        assert!(!matches!(status, ExitReason::Succeed(_)));
+        evm.revert(snapshot);
+        let (_, status, gas, _) = evm
+            .call_raw_with_gas(Address::zero(), addr, calldata.clone(), 0.into(), estimate)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert!(estimate > gas);
+
+        let compiled = COMPILED.find("Thrower").expect("could not find contract");
+        let (thrower, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let err = evm
+            .estimate_gas(Address::zero(), thrower, id("fail()").to_vec().into(), 0.into())
+            .unwrap_err();
+        assert!(err.to_string().ends_with("inner"), "{}", err);
+    }
+
+    #[test]
+    fn recording() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let bytecode = compiled.bytecode().unwrap().clone();
+        let (addr, _, _, _) = evm.deploy(Address::zero(), bytecode.clone(), 0.into()).unwrap();
+
+        evm.start_recording();
+        let alice = Address::random();
+        let gm: Bytes = id("gm()").to_vec().into();
+        evm.call_raw(alice, addr, gm.clone(), 0.into(), false).unwrap();
+        let (deployed, _, _, _) = evm.deploy(alice, bytecode.clone(), 0.into()).unwrap();
+        evm.call::<(), _, _>(alice, deployed, "gm()", (), 0.into(), None).unwrap();
+        evm.estimate_gas(alice, addr, gm.clone(), 0.into()).unwrap();
+
+        let call = |to| RecordedTx {
+            from: alice,
+            to: Some(to),
+            data: gm.clone(),
+            value: 0.into(),
+            salt: None,
+        };
+        assert_eq!(
+            evm.take_recording(),
+            vec![
+                call(addr),
+                RecordedTx { from: alice, to: None, data: bytecode, value: 0.into(), salt: None },
+                call(deployed),
+            ]
+        );
+        assert!(evm.take_recording().is_empty());
+
+        evm.stop_recording();
+        evm.call_raw(alice, addr, gm, 0.into(), false).unwrap();
+        assert!(evm.take_recording().is_empty());
+    }
+
+    #[test]
+    fn cumulative_gas_used() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, deploy_gas, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        assert_eq!(evm.cumulative_gas_used(), deploy_gas);
+        evm.reset_gas_counter();
+
+        let gm: Bytes = id("gm()").to_vec().into();
+        let (_, _, first, _) =
+            evm.call_raw(Address::zero(), addr, gm.clone(), 0.into(), false).unwrap();
+        let (_, _, second, _) = evm
+            .call::<(), _, _>(
+                Address::zero(),
+                addr,
+                "greet(string)",
+                "hi".to_string(),
+                0.into(),
+                None,
+            )
+            .unwrap();
+        evm.estimate_gas(Address::zero(), addr, gm, 0.into()).unwrap();
+        assert_eq!(evm.cumulative_gas_used(), first + second);
+    }
+
+    #[test]
+    fn load_genesis() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("ConstructorArgs").expect("could not find contract");
+        let code = compiled.bin_runtime.unwrap().clone().into_bytes().unwrap();
+        let (contract, owner) = (Address::random(), Address::random());
+        let alloc = HashMap::from([
+            (
+                contract,
+                GenesisAccount {
+                    code,
+                    storage: BTreeMap::from([
+                        (H256::zero(), H256::from_low_u64_be(42)),
+                        (H256::from_low_u64_be(1), owner.into()),
+                    ]),
+                    ..Default::default()
+                },
+            ),
+            (owner, GenesisAccount { balance: 1000.into(), nonce: 3.into(), ..Default::default() }),
+        ]);
+        evm.load_genesis(alloc).unwrap();
+
+        let owner_account = evm.state().basic(owner);
+        assert_eq!(owner_account.balance, 1000.into());
+        assert_eq!(owner_account.nonce, 3.into());
+        let (value, _, _, _) = evm
+            .call::<U256, _, _>(owner, contract, "value()(uint256)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(value, 42.into());
+        let (stored_owner, _, _, _) = evm
+            .call::<Address, _, _>(owner, contract, "owner()(address)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(stored_owner, owner);
+
+        // invalid nonces are rejected before any account is seeded
+        let (fresh, current) = (Address::random(), evm.state().basic(owner));
+        for (nonce, err) in [
+            (
+                2.into(),
+                format!("cannot decrease the nonce of {:?} from {} to 2", owner, current.nonce),
+            ),
+            (U256::MAX, format!("the nonce {} of {:?} does not fit in 64 bits", U256::MAX, owner)),
+        ] {
+            let alloc = HashMap::from([
+                (fresh, GenesisAccount { balance: 1.into(), ..Default::default() }),
+                (owner, GenesisAccount { nonce, ..Default::default() }),
+            ]);
+            assert_eq!(evm.load_genesis(alloc).unwrap_err().to_string(), err);
+            assert_eq!(evm.state().basic(owner).balance, current.balance);
+            assert_eq!(evm.state().basic(fresh).balance, 0.into());
+        }
+    }
+
+    #[test]
+    fn create2_deployer() {
+        let mut evm = vm();
+        evm.install_create2_deployer();
+
+        let initcode = COMPILED.find("Greeter").unwrap().bytecode().unwrap().clone();
+        let salt = H256::random();
+        let calldata = [salt.as_bytes(), initcode.as_ref()].concat();
+        let (retdata, status, _, _) = evm
+            .call_raw(Address::zero(), CREATE2_DEPLOYER, calldata.into(), 0.into(), false)
+            .unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+
+        let expected = ethers::utils::get_create2_address(
+            CREATE2_DEPLOYER,
+            salt.as_bytes().to_vec(),
+            initcode,
+        );
+        assert_eq!(Address::from_slice(&retdata), expected);
+        assert!(!evm.code(expected).is_empty());
+    }
+
+    #[test]
+    fn gas_refund_cap() {
+        let (gas_used, refund) = (U256::from(100_000), U256::from(60_000));
+        assert_eq!(capped_refund(&Config::berlin(), gas_used, refund), 50_000.into());
+        assert_eq!(capped_refund(&Config::london(), gas_used, refund), 20_000.into());
+
+        // all slots are set in the backend, so clearing them is refunded
+        let compiled = COMPILED.find("StorageClearing").expect("could not find contract");
+        let code = compiled.bin_runtime.unwrap().clone().into_bytes().unwrap().to_vec();
+        let addr = Address::from_low_u64_be(0x1337);
+        let storage = (0..10)
+            .map(|slot| (H256::from_low_u64_be(slot), H256::from_low_u64_be(1)))
+            .collect::<BTreeMap<_, _>>();
+        let account = MemoryAccount { nonce: 0.into(), balance: 0.into(), code, storage };
+
+        let mut refunds = Vec::new();
+        for config in [Config::berlin(), Config::london()] {
+            let backend = new_backend(&*VICINITY, BTreeMap::from([(addr, account.clone())]));
+            let mut evm = Executor::new_with_cheatcodes(
+                backend,
+                GAS_LIMIT,
+                &config,
+                &*PRECOMPILES_MAP,
+                true,
+                false,
+                false,
+            );
+            let calldata = id("clear()").to_vec().into();
+            let (_, receipt) =
+                evm.call_raw_with_receipt(Address::zero(), addr, calldata, 0.into()).unwrap();
+            assert!(matches!(receipt.status, ExitReason::Succeed(_)));
+            assert!(receipt.gas_refund.capped < receipt.gas_refund.uncapped);
+            refunds.push(receipt.gas_refund);
+        }
+
+        // clearing a slot is refunded 15000 gas before London and 4800 gas after
+        assert_eq!(refunds[0].uncapped, 10 * 15_000);
+        assert_eq!(refunds[1].uncapped, 10 * 4_800);
+        // the cap is lowered from half to a fifth of the gas used
+        assert!(refunds[1].capped < refunds[0].capped);
+    }
+
+    #[test]
+    fn batch_balances() {
+        let accounts = (1..=12).map(Address::from_low_u64_be).collect::<Vec<_>>();
+        let balances = accounts
+            .iter()
+            .enumerate()
+            .map(|(i, address)| (*address, U256::from(i * 100)))
+            .collect::<Vec<_>>();
+
+        let mut batched = vm();
+        batched.set_balances(&balances);
+        let mut sequential = vm();
+        for (address, balance) in &balances {
+            sequential.set_balance(*address, *balance);
+        }
+
+        let expected = balances.iter().map(|(_, balance)| *balance).collect::<Vec<_>>();
+        assert_eq!(batched.get_balances(&accounts), expected);
+        assert_eq!(sequential.get_balances(&accounts), expected);
+        assert!(batched.get_balances(&[]).is_empty());
+    }
+
+    #[test]
+    fn set_balances_keeps_code_and_nonce() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (greeter, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let code = evm.code(greeter);
+
+        evm.set_balances(&[(greeter, 5.into())]);
+        assert_eq!(evm.get_balances(&[greeter]), vec![5.into()]);
+        assert_eq!(evm.code(greeter), code);
+        assert_eq!(evm.get_nonce(greeter), 1);
+    }
+
+    #[test]
+    fn nonces() {
+        let mut evm = vm();
+        let sender = Address::random();
+        assert_eq!(evm.get_nonce(sender), 0);
+        evm.set_nonce(sender, 5).unwrap();
+        assert_eq!(evm.get_nonce(sender), 5);
+        let err = evm.set_nonce(sender, 4).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("cannot decrease the nonce of {:?} from 5 to 4", sender)
+        );
+        assert_eq!(evm.get_nonce(sender), 5);
+
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(sender, compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        assert_eq!(addr, ethers::utils::get_contract_address(sender, 5u64));
+        assert_eq!(evm.get_nonce(sender), 6);
+
+        let err = evm.set_nonce(sender, u64::MAX).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "cannot raise the nonce of {:?} from 6 to {}, by more than {} at once",
+                sender,
+                u64::MAX,
+                MAX_NONCE_INCREMENT
+            )
+        );
+        evm.set_nonce(sender, 6 + MAX_NONCE_INCREMENT).unwrap();
+        assert_eq!(evm.get_nonce(sender), 6 + MAX_NONCE_INCREMENT);
+    }
+
+    #[test]
+    fn read_and_write_storage() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("CallTarget").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        assert_eq!(evm.read_storage(addr, 0.into()), 0.into());
+
+        evm.write_storage(addr, 0.into(), 42.into());
+        assert_eq!(evm.read_storage(addr, 0.into()), 42.into());
+        let (x, _, _, _) = evm
+            .call::<U256, _, _>(Address::zero(), addr, "x()(uint256)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(x, 42.into());
+    }
+
+    #[test]
+    fn deploy_many() {
+        let mut evm = vm();
+        let greeter = COMPILED.find("Greeter").unwrap().bytecode().unwrap().clone();
+        let thrower = COMPILED.find("Thrower").unwrap().bytecode().unwrap().clone();
+        let codes = vec![greeter.clone(), thrower, greeter];
+
+        let deployed = evm.deploy_many(Address::zero(), codes, 0.into()).unwrap();
+        assert_eq!(deployed.len(), 3);
+        assert!(deployed.iter().all(|(address, gas)| !evm.code(*address).is_empty() && *gas > 0));
+        let addresses = deployed.iter().map(|(address, _)| *address).collect::<BTreeSet<_>>();
+        assert_eq!(addresses.len(), 3);
+
+        let codes =
+            vec![COMPILED.find("Greeter").unwrap().bytecode().unwrap().clone(), vec![0xfe].into()];
+        let err = evm.deploy_many(Address::zero(), codes, 0.into()).unwrap_err();
+        assert_eq!(err.to_string(), "deployment 1 failed");
+    }
+
+    #[test]
+    fn deploy_with_args() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("ConstructorArgs").expect("could not find contract");
+        let bytecode = compiled.bytecode().unwrap().clone();
+        let constructor = compiled.abi.unwrap().constructor().unwrap();
+        let owner = Address::random();
+        let (addr, _, _, _) = evm
+            .deploy_with_args(
+                Address::zero(),
+                bytecode.clone(),
+                constructor,
+                (U256::from(42), owner),
+                0.into(),
+            )
+            .unwrap();
+        let (value, _, _, _) = evm
+            .call::<U256, _, _>(Address::zero(), addr, "value()(uint256)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(value, 42.into());
+        let (deployed_owner, _, _, _) = evm
+            .call::<Address, _, _>(Address::zero(), addr, "owner()(address)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(deployed_owner, owner);
+
+        // the arguments must match the constructor
+        assert!(evm
+            .deploy_with_args(Address::zero(), bytecode, constructor, (), 0.into())
+            .is_err());
+
+        let greeter = COMPILED.find("Greeter").unwrap().bytecode().unwrap().clone();
+        let no_args = ethers::abi::Constructor { inputs: vec![] };
+        let (addr, _, _, _) =
+            evm.deploy_with_args(Address::zero(), greeter, &no_args, (), 0.into()).unwrap();
+        assert!(!evm.code(addr).is_empty());
+    }
+
+    #[test]
+    fn set_code() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (greeter, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let stub = Address::random();
+        evm.set_code(stub, evm.code(greeter).into());
+        assert_eq!(evm.code(stub), evm.code(greeter));
+        evm.call::<(), _, _>(
+            Address::zero(),
+            stub,
+            "greet(string)",
+            "hi".to_owned(),
+            0.into(),
+            None,
+        )
+        .unwrap();
+        let (greeting, _, _, _) = evm
+            .call::<String, _, _>(Address::zero(), stub, "greeting()(string)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(greeting, "hi");
+
+        evm.set_code(stub, Bytes::default());
+        assert!(evm.code(stub).is_empty());
+        let err = evm
+            .call::<String, _, _>(Address::zero(), stub, "greeting()(string)", (), 0.into(), None)
+            .unwrap_err();
+        assert!(matches!(err, EvmError::NoCode { address } if address == stub));
+    }
+
+    #[test]
+    fn call_with_gas_limit() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let func = IntoFunction::into("greet(string)");
+        let calldata = ethers::contract::encode_function_data(&func, "hi".to_owned()).unwrap();
+
+        let (_, status, _, _) = evm
+            .call_raw_with_gas(Address::zero(), addr, calldata.clone(), 0.into(), 30_000)
+            .unwrap();
+        assert_eq!(status, ExitReason::Error(ExitError::OutOfGas));
+        assert_eq!(evm.gas_limit, GAS_LIMIT);
+
+        let (_, status, _, _) =
+            evm.call_raw_with_gas(Address::zero(), addr, calldata, 0.into(), 100_000).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+    }
+
+    #[test]
+    fn snapshot_and_revert() {
+        let mut evm = vm();
+        let (alice, bob) = (Address::random(), Address::random());
+        evm.set_balance(alice, 10.into());
+        evm.call_raw(alice, bob, Bytes::default(), 3.into(), false).unwrap();
+
+        let id = evm.snapshot();
+        let later = evm.snapshot();
+        evm.call_raw(alice, bob, Bytes::default(), 4.into(), false).unwrap();
+        evm.set_balance(bob, 100.into());
+        assert_eq!(evm.get_balances(&[alice, bob]), vec![3.into(), 107.into()]);
+
+        assert!(evm.revert(id));
+        assert_eq!(evm.get_balances(&[alice, bob]), vec![7.into(), 3.into()]);
+        assert_eq!(evm.get_nonce(alice), 1);
+        // the snapshot and all later ones are gone
+        assert!(!evm.revert(id));
+        assert!(!evm.revert(later));
+    }
+
+    #[test]
+    fn deploy_create2() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let code = compiled.bytecode().unwrap().clone();
+        let sender = Address::random();
+        evm.set_nonce(sender, 3).unwrap();
+
+        let salt = U256::from(1);
+        let (addr, _, _, _) = evm.deploy_create2(sender, code.clone(), salt, 0.into()).unwrap();
+        let salt_bytes = H256::from_low_u64_be(1).as_bytes().to_vec();
+        let expected = ethers::utils::get_create2_address(sender, salt_bytes, code.clone());
+        assert_eq!(addr, expected);
+        evm.call::<(), _, _>(Address::zero(), addr, "gm()", (), 0.into(), None).unwrap();
+
+        // the address is taken
+        assert!(evm.deploy_create2(sender, code.clone(), salt, 0.into()).is_err());
+        let (other, _, _, _) = evm.deploy_create2(sender, code, 2.into(), 0.into()).unwrap();
+        assert_ne!(other, addr);
+    }
+
+    #[test]
+    fn deploy_with_code() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _, code) = evm
+            .deploy_with_code(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+            .unwrap();
+        assert!(!code.is_empty());
+        let runtime_code = compiled.bin_runtime.unwrap().clone().into_bytes().unwrap();
+        assert_eq!(code, runtime_code);
+        assert_eq!(code.to_vec(), evm.code(addr));
+
+        let compiled = COMPILED.find("RevertingConstructor").expect("could not find contract");
+        assert!(evm
+            .deploy_with_code(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+            .is_err());
+    }
+
+    #[test]
+    fn storage_layout() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("StorageLayout").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let packed = (U256::from(3) << 128) | U256::from(2);
+        let mut layout = vec![(0.into(), 1.into()), (1.into(), packed), (2.into(), 0x1337.into())];
+        evm.assert_storage_layout(addr, &layout).unwrap();
+
+        layout[0].1 = 2.into();
+        layout[2].1 = 0x1338.into();
+        layout.push((3.into(), 0.into()));
+        let err = evm.assert_storage_layout(addr, &layout).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "storage of {:?} does not match the expected layout:\n\
+                 slot 0x0: expected 0x2, got 0x1\n\
+                 slot 0x2: expected 0x1338, got 0x1337",
+                addr
+            )
+        );
+    }
+
+    #[test]
+    fn initcode_size_limit() {
+        let mut evm = vm();
+        // returns a single byte of code
+        let initcode = |len| {
+            let mut initcode = vec![0x60, 0x01, 0x60, 0x00, 0xf3];
+            initcode.resize(len, 0);
+            Bytes::from(initcode)
+        };
+        let oversized = initcode(MAX_INITCODE_SIZE + 1);
+        evm.deploy(Address::zero(), oversized.clone(), 0.into()).unwrap();
+
+        evm.max_initcode_size = Some(MAX_INITCODE_SIZE);
+        let err = evm.deploy(Address::zero(), oversized, 0.into()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EvmError>(),
+            Some(EvmError::InitCodeSizeExceeded { size, limit: MAX_INITCODE_SIZE })
+                if *size == MAX_INITCODE_SIZE + 1
+        ));
+        let (_, _, metered_gas, _) = evm.deploy(Address::zero(), initcode(64), 0.into()).unwrap();
+
+        evm.max_initcode_size = None;
+        let (_, _, gas, _) = evm.deploy(Address::zero(), initcode(64), 0.into()).unwrap();
+        assert_eq!(metered_gas, gas + 4);
+    }
+
+    #[test]
+    fn deploy_gas() {
+        let mut evm = vm();
+        // returns `len` zero bytes of code
+        let initcode = |len: u16| {
+            let [hi, lo] = len.to_be_bytes();
+            Bytes::from(vec![0x61, hi, lo, 0x60, 0x00, 0xf3])
+        };
+        // 53000 create transaction cost - 21000 base cost + 4 * 16 + 2 * 4 calldata cost +
+        // 2 * 3 for the pushes + memory expansion + 200 per deposited byte
+        let (_, _, gas, _) = evm.deploy(Address::zero(), initcode(256), 0.into()).unwrap();
+        assert_eq!(gas, 32000 + 72 + 6 + 24 + 256 * 200);
+        let (_, _, gas, _) = evm.deploy(Address::zero(), initcode(512), 0.into()).unwrap();
+        assert_eq!(gas, 32000 + 72 + 6 + 48 + 512 * 200);
+
+        // deploys a child returning 32 bytes of code with CREATE2, then returns one byte of code
+        let factory = [
+            // mstore(0, <PUSH1 0x20 PUSH1 0x00 RETURN>)
+            &[0x64, 0x60, 0x20, 0x60, 0x00, 0xf3, 0x60, 0x00, 0x52][..],
+            // pop(create2(0, 27, 5, 0))
+            &[0x60, 0x00, 0x60, 0x05, 0x60, 0x1b, 0x60, 0x00, 0xf5, 0x50],
+            // return(0, 1)
+            &[0x60, 0x01, 0x60, 0x00, 0xf3],
+        ]
+        .concat();
+        let (_, _, gas, _) = evm.deploy(Address::zero(), factory.into(), 0.into()).unwrap();
+        // the child costs 2 * 3 for the pushes + 3 for memory + 32 * 200 for its code, CREATE2
+        // costs 32000 + 6 per word of hashed init code
+        let child = 6 + 3 + 32 * 200;
+        let factory = 12 + 12 + (32000 + 6) + child + 2 + 6 + 200;
+        assert_eq!(gas, 32000 + 19 * 16 + 5 * 4 + factory);
+    }
+
+    #[test]
+    fn try_deploy_failures() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("RevertingConstructor").expect("could not find contract");
+        let err = evm
+            .try_deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            EvmError::Execution { ref reason, revert_data: Some(_), .. }
+                if reason == "constructor failed"
+        ));
+
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let gas_limit = std::mem::replace(&mut evm.gas_limit, 60_000);
+        let err = evm
+            .try_deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+            .unwrap_err();
+        assert!(
+            matches!(err, EvmError::Execution { ref reason, .. } if reason.contains("OutOfGas"))
+        );
+        evm.gas_limit = gas_limit;
+
+        // returns 0x6001 bytes of code, exceeding the limit of EIP-170
+        let oversized = Bytes::from(vec![0x61, 0x60, 0x01, 0x60, 0x00, 0xf3]);
+        let err = evm.try_deploy(Address::zero(), oversized, 0.into()).unwrap_err();
+        assert!(matches!(
+            err,
+            EvmError::Execution { ref reason, .. } if reason.contains("CreateContractLimit")
+        ));
+
+        // init code which returns no code
+        let err = evm.try_deploy(Address::zero(), vec![0u8].into(), 0.into()).unwrap_err();
+        assert!(matches!(err, EvmError::Execution { revert_data: None, .. }));
+        let res = evm
+            .try_deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+            .unwrap();
+        assert!(!evm.code(res.address).is_empty());
+    }
+
+    #[test]
+    fn setup_gas_limit() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("HeavySetup").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        evm.gas_limit = 1_000_000;
+        let state = evm.state().clone();
+        let err = evm.setup(addr).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EvmError>(),
+            Some(EvmError::SetupOutOfGas { gas_limit: 1_000_000, .. })
+        ));
+
+        evm.reset(state);
+        evm.setup_gas_limit = Some(crate::sputnik::helpers::GAS_LIMIT);
+        let status = evm.setup(addr).unwrap().0;
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Stopped));
+        assert_eq!(evm.gas_limit, 1_000_000);
+
+        evm.call::<(), _, _>(Address::zero(), addr, "testValues()", (), 0.into(), compiled.abi)
+            .unwrap();
+    }
+
+    #[test]
+    fn setup_with_abi() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("RevertingSetup").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let res = evm.setup_with_abi(addr, compiled.abi.unwrap()).unwrap();
+        assert_eq!(
+            res,
+            SetupResult::Reverted { reason: "setup failed".to_string(), logs: Vec::new() }
+        );
+
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let res = evm.setup_with_abi(addr, compiled.abi.unwrap()).unwrap();
+        assert_eq!(res, SetupResult::NotPresent);
+
+        let compiled = COMPILED.find("HeavySetup").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let res = evm.setup_with_abi(addr, compiled.abi.unwrap()).unwrap();
+        assert_eq!(res, SetupResult::Success { logs: Vec::new() });
+    }
+
+    #[test]
+    fn typed_call_to_eoa() {
+        let mut evm = vm();
+        let eoa = Address::from_low_u64_be(0x1234);
+        let err = evm
+            .call::<String, _, _>(Address::zero(), eoa, "greeting()(string)", (), 0.into(), None)
+            .unwrap_err();
+        assert_eq!(err.to_string(), format!("call to account with no code at {:?}", eoa));
+
+        // calls without return data still succeed
+        evm.call::<(), _, _>(Address::zero(), eoa, "gm()", (), 0.into(), None).unwrap();
+    }
+
+    #[test]
+    fn call_without_value() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        evm.call_no_value::<(), _, _>(Address::zero(), addr, "greet(string)", "hi".to_owned())
+            .unwrap();
+        let (greeting, _, _, _) = evm
+            .call_no_value::<String, _, _>(Address::zero(), addr, "greeting()(string)", ())
+            .unwrap();
+        assert_eq!(greeting, "hi");
+    }
+
+    #[test]
+    fn call_assert_eq() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        evm.call_no_value::<(), _, _>(Address::zero(), addr, "greet(string)", "hi".to_owned())
+            .unwrap();
+
+        let greeting = "greeting()(string)";
+        evm.call_assert_eq(Address::zero(), addr, greeting, (), 0.into(), None, "hi".to_owned())
+            .unwrap();
+        let err = evm
+            .call_assert_eq(Address::zero(), addr, greeting, (), 0.into(), None, "gm".to_owned())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "return value mismatch: `left != right`\n  left: `\"hi\"`,\n right: `\"gm\"`"
+        );
+
+        // reverts surface with their reason instead of failing to decode
+        let compiled = COMPILED.find("Thrower").expect("could not find contract");
+        let (thrower, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let err = evm
+            .call_assert_eq(Address::zero(), thrower, "fail()", (), 0.into(), None, ())
+            .unwrap_err();
+        assert!(matches!(err, EvmError::Execution { ref reason, .. } if reason == "inner"));
+    }
+
+    #[test]
+    fn call_unchecked_returns_revert_data() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Thrower").expect("could not find contract");
+        let (thrower, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let func = IntoFunction::into("fail()");
+        let (retdata, status, _, _) =
+            evm.call_unchecked(Address::zero(), thrower, &func, (), 0.into()).unwrap();
+        assert_eq!(status, ExitReason::Revert(ExitRevert::Reverted));
+        assert_eq!(foundry_utils::decode_revert(retdata.as_ref(), None).unwrap(), "inner");
+    }
+
+    #[test]
+    fn check_success_with_custom_failure() {
+        fn did_revert(
+            evm: &mut TestSputnikVM<'_, MemoryBackend<'_>>,
+            address: Address,
+        ) -> eyre::Result<bool> {
+            let sig = "didRevert()(bool)";
+            let (failed, _, _, _) =
+                evm.call::<bool, _, _>(Address::zero(), address, sig, (), 0.into(), None)?;
+            Ok(failed)
+        }
+
+        let mut evm = vm();
+        let compiled = COMPILED.find("CustomAssertions").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let success = ExitReason::Succeed(ExitSucceed::Stopped);
+        assert!(evm.check_success_with(addr, &success, false, did_revert));
+        evm.call::<(), _, _>(Address::zero(), addr, "recordFailure()", (), 0.into(), None).unwrap();
+        assert!(!evm.check_success_with(addr, &success, false, did_revert));
+        assert!(evm.check_success_with(addr, &success, true, did_revert));
+        // the contract has no ds-test `failed()`, so the default check cannot tell
+        assert!(evm.check_success(addr, &success, false));
+    }
+
+    #[test]
+    fn call_by_selector() {
+        use ethers::abi::ParamType;
+
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let mut selector = [0u8; 4];
+        selector.copy_from_slice(&id("greet(string)"));
+        evm.call_selector::<(), _>(
+            Address::zero(),
+            addr,
+            selector,
+            "hi".to_owned(),
+            0.into(),
+            &[ParamType::String],
+            &[],
+        )
+        .unwrap();
+
+        selector.copy_from_slice(&id("greeting()"));
+        let (greeting, _, _, _) = evm
+            .call_selector::<String, _>(
+                Address::zero(),
+                addr,
+                selector,
+                (),
+                0.into(),
+                &[],
+                &[ParamType::String],
+            )
+            .unwrap();
+        assert_eq!(greeting, "hi");
+
+        // argument count and types are validated before executing
+        let err = evm
+            .call_selector::<String, _>(
+                Address::zero(),
+                addr,
+                selector,
+                U256::from(1),
+                0.into(),
+                &[],
+                &[ParamType::String],
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("expected 0 arguments"));
+        let err = evm
+            .call_selector::<(), _>(
+                Address::zero(),
+                addr,
+                selector,
+                U256::from(1),
+                0.into(),
+                &[ParamType::String],
+                &[],
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("is not of type string"));
+
+        // like `call`, calls to accounts without code are reported
+        let stub = Address::random();
+        let err = evm
+            .call_selector::<String, _>(
+                Address::zero(),
+                stub,
+                selector,
+                (),
+                0.into(),
+                &[],
+                &[ParamType::String],
+            )
+            .unwrap_err();
+        assert!(matches!(err, EvmError::NoCode { address } if address == stub));
     }
 
     #[test]
@@ -450,6 +2247,144 @@ mod tests {
         assert_eq!(gas_used, 26569);
     }
 
+    #[test]
+    fn raw_revert_data() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("ExpectRevert").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let nonce = evm.state().basic(Address::zero()).nonce;
+        let err = evm
+            .call::<U256, _, _>(
+                Address::zero(),
+                addr,
+                "customErr(uint256)(uint256)",
+                U256::from(100),
+                0.into(),
+                compiled.abi,
+            )
+            .unwrap_err();
+        let revert_data = match err {
+            crate::EvmError::Execution { revert_data, .. } => revert_data,
+            _ => panic!("unexpected error variant"),
+        };
+        assert_eq!(revert_data.unwrap().as_ref(), id("InputTooLarge()"));
+        // the reverted call was still committed
+        assert_eq!(evm.state().basic(Address::zero()).nonce, nonce + 1);
+    }
+
+    #[test]
+    fn decode_custom_error_from_revert_data() {
+        use ethers::abi::Token;
+
+        let mut evm = vm();
+        let compiled = COMPILED.find("Vault").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let func = "withdraw(uint256)";
+        let err = evm
+            .call::<(), _, _>(Address::zero(), addr, func, U256::from(10), 0.into(), compiled.abi)
+            .unwrap_err();
+        let (reason, revert_data) = match err {
+            EvmError::Execution { reason, revert_data, .. } => (reason, revert_data.unwrap()),
+            _ => panic!("unexpected error variant"),
+        };
+        assert_eq!(reason, "InsufficientBalance(0, 10)");
+
+        let error =
+            compiled.abi.unwrap().errors().find(|error| error.name == "InsufficientBalance");
+        let error = error.unwrap();
+        assert_eq!(revert_data[..4], error.signature()[..4]);
+        let fields = error.decode(&revert_data[4..]).unwrap();
+        assert_eq!(fields, vec![Token::Uint(0.into()), Token::Uint(10.into())]);
+    }
+
+    #[test]
+    fn transact_validates_nonces() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (addr, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        let from = Address::random();
+        let calldata: Bytes = id("gm()").to_vec().into();
+
+        // correct sequence
+        evm.transact(from, addr, calldata.clone(), 0.into(), 0.into()).unwrap();
+        evm.transact(from, addr, calldata.clone(), 0.into(), 1.into()).unwrap();
+
+        // reuse
+        let err = evm.transact(from, addr, calldata.clone(), 0.into(), 1.into()).unwrap_err();
+        match err {
+            crate::EvmError::NonceTooLow { expected, provided } => {
+                assert_eq!(expected, 2.into());
+                assert_eq!(provided, 1.into());
+            }
+            _ => panic!("unexpected error variant"),
+        }
+
+        // gap
+        let err = evm.transact(from, addr, calldata.clone(), 0.into(), 5.into()).unwrap_err();
+        assert_eq!(err.to_string(), "nonce too high: expected 2, got 5");
+
+        // out-of-order replay is allowed if the checks are disabled
+        evm.nonce_checks = false;
+        evm.transact(from, addr, calldata, 0.into(), 5.into()).unwrap();
+    }
+
+    #[test]
+    fn impersonate_contract() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Whale").expect("could not find contract");
+        let (whale, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let (token, _, _, _) = evm
+            .call::<Address, _, _>(Address::zero(), whale, "token()(address)", (), 0.into(), None)
+            .unwrap();
+
+        let alice = Address::random();
+        let func = IntoFunction::into("transfer(address,uint256)");
+        let calldata =
+            ethers::contract::encode_function_data(&func, (alice, U256::from(100))).unwrap();
+        // the whale's nonce was incremented when it created the token
+        assert!(matches!(
+            evm.transact(whale, token, calldata.clone(), 0.into(), 0.into()),
+            Err(EvmError::NonceTooLow { .. })
+        ));
+
+        evm.impersonate(whale);
+        let (_, status, _, _) =
+            evm.transact(whale, token, calldata.clone(), 0.into(), 0.into()).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        let balance_of = "balanceOf(address)(uint256)";
+        let (balance, _, _, _) =
+            evm.call::<U256, _, _>(alice, token, balance_of, alice, 0.into(), None).unwrap();
+        assert_eq!(balance, 100.into());
+        let (balance, _, _, _) =
+            evm.call::<U256, _, _>(alice, token, balance_of, whale, 0.into(), None).unwrap();
+        assert_eq!(balance, 900.into());
+
+        evm.stop_impersonate(whale);
+        assert!(evm.transact(whale, token, calldata, 0.into(), 0.into()).is_err());
+    }
+
+    #[test]
+    fn impersonate_unfunded_account() {
+        let mut evm = vm();
+        let (sender, recipient) = (Address::random(), Address::random());
+        let (_, status, _, _) =
+            evm.transact(sender, recipient, Bytes::default(), 100.into(), 0.into()).unwrap();
+        assert!(matches!(status, ExitReason::Error(ExitError::OutOfFund)));
+
+        evm.impersonate(sender);
+        let (_, status, _, _) =
+            evm.transact(sender, recipient, Bytes::default(), 100.into(), 5.into()).unwrap();
+        assert!(matches!(status, ExitReason::Succeed(_)));
+        assert_eq!(evm.get_balances(&[sender, recipient]), vec![0.into(), 100.into()]);
+    }
+
     #[test]
     fn test_can_call_large_contract() {
         let mut evm = vm();
@@ -0,0 +1,79 @@
+//! Deterministic exports of an executor's committed state
+use serde_json::{Map, Value};
+use sputnik::backend::Backend;
+
+use crate::sputnik::{
+    cheatcodes::memory_stackstate_owned::MemoryStackStateOwned, Executor, SputnikExecutor,
+};
+
+impl<'a, B: Backend, E> Executor<MemoryStackStateOwned<'a, B>, E>
+where
+    E: SputnikExecutor<MemoryStackStateOwned<'a, B>>,
+{
+    /// Exports every account modified during execution, with its balance, nonce, code and
+    /// written storage slots, as a JSON object keyed by address.
+    ///
+    /// Accounts and storage slots are emitted in ascending order, so exporting the same state
+    /// always yields the same output, which makes it suitable for CI diffing and hashing.
+    pub fn to_sorted_json(&self) -> Value {
+        let state = self.executor.state();
+
+        let accounts = state
+            .touched_storage()
+            .into_iter()
+            .map(|(address, slots)| {
+                let basic = state.basic(address);
+                let storage = slots
+                    .into_iter()
+                    .map(|slot| {
+                        let value = state.storage(address, slot);
+                        (format!("{:?}", slot), Value::from(format!("{:?}", value)))
+                    })
+                    .collect::<Map<_, _>>();
+
+                let mut account = Map::new();
+                account.insert("balance".to_string(), Value::from(format!("{:#x}", basic.balance)));
+                account.insert("nonce".to_string(), Value::from(format!("{:#x}", basic.nonce)));
+                account.insert(
+                    "code".to_string(),
+                    Value::from(format!("0x{}", hex::encode(state.code(address)))),
+                );
+                account.insert("storage".to_string(), Value::Object(storage));
+                (format!("{:?}", address), Value::Object(account))
+            })
+            .collect::<Map<_, _>>();
+
+        Value::Object(accounts)
+    }
+
+    /// Serializes [`to_sorted_json`](Self::to_sorted_json) to bytes
+    pub fn to_sorted_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.to_sorted_json()).expect("JSON values are always serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sputnik::helpers::vm, test_helpers::COMPILED, Evm};
+    use ethers::types::Address;
+
+    #[test]
+    fn sorted_export_is_reproducible() {
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+
+        let mut exports = Vec::new();
+        for _ in 0..2 {
+            let mut evm = vm();
+            let (addr, _, _, _) = evm
+                .deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into())
+                .unwrap();
+            evm.call::<(), _, _>(Address::zero(), addr, "gm()", (), 0.into(), None).unwrap();
+
+            let export = evm.to_sorted_bytes();
+            assert_eq!(export, evm.to_sorted_bytes());
+            exports.push(export);
+        }
+        assert_eq!(exports[0], exports[1]);
+    }
+}
@@ -5,6 +5,7 @@ use ethers::{
     providers::Middleware,
     types::{Address, BlockId, Bytes, TxHash, H160, H256, U256},
 };
+use eyre::WrapErr;
 use futures::{
     channel::mpsc::{channel, Receiver, Sender},
     stream::{Fuse, Stream, StreamExt},
@@ -12,8 +13,11 @@ use futures::{
     Future, FutureExt,
 };
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{hash_map::Entry, BTreeMap, HashMap},
+    fs,
+    path::Path,
     pin::Pin,
     sync::{
         mpsc::{channel as oneshot_channel, Sender as OneshotSender},
@@ -43,6 +47,68 @@ pub fn new_shared_cache<T>(cache: T) -> SharedCache<T> {
     Arc::new(RwLock::new(cache))
 }
 
+/// A [`MemCache`] as written to disk by [`flush_cache_to`]
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    /// The block the accounts were fetched at
+    block: u64,
+    accounts: BTreeMap<H160, CachedAccount>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedAccount {
+    nonce: U256,
+    balance: U256,
+    code: Bytes,
+    storage: BTreeMap<H256, H256>,
+}
+
+/// Writes the cache of a fork pinned at `block` to `path` as JSON, so that later runs against
+/// the same block can start from it via [`load_cache_from`] instead of fetching every account
+/// again
+pub fn flush_cache_to(cache: &MemCache, block: u64, path: impl AsRef<Path>) -> eyre::Result<()> {
+    let accounts = cache
+        .iter()
+        .map(|(address, account)| {
+            let account = CachedAccount {
+                nonce: account.nonce,
+                balance: account.balance,
+                code: account.code.clone().into(),
+                storage: account.storage.clone(),
+            };
+            (*address, account)
+        })
+        .collect();
+    let content = serde_json::to_string(&CacheFile { block, accounts })?;
+    Ok(fs::write(path, content)?)
+}
+
+/// Reads a cache written by [`flush_cache_to`]. A cache of a different block than `block` is
+/// stale, so an empty cache is returned instead.
+pub fn load_cache_from(path: impl AsRef<Path>, block: u64) -> eyre::Result<MemCache> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .wrap_err(format!("failed to read fork cache \"{}\"", path.display()))?;
+    let file: CacheFile = serde_json::from_str(&content)?;
+    if file.block != block {
+        return Ok(MemCache::default())
+    }
+    let cache = file
+        .accounts
+        .into_iter()
+        .map(|(address, account)| {
+            let account = MemoryAccount {
+                nonce: account.nonce,
+                balance: account.balance,
+                code: account.code.to_vec(),
+                storage: account.storage,
+            };
+            (address, account)
+        })
+        .collect();
+    Ok(cache)
+}
+
 type AccountFuture<Err> =
     Pin<Box<dyn Future<Output = (Result<(U256, U256, Bytes), Err>, Address)> + Send>>;
 type StorageFuture<Err> = Pin<Box<dyn Future<Output = (Result<H256, Err>, Address, H256)> + Send>>;
@@ -505,7 +571,7 @@ struct SharedBackendInner {
 
 #[cfg(test)]
 mod tests {
-    use crate::sputnik::vicinity;
+    use crate::sputnik::{helpers::new_vicinity, vicinity};
     use ethers::{
         providers::{Http, Provider},
         types::Address,
@@ -549,4 +615,29 @@ mod tests {
         let mem_acc = cache.read().get(&address).unwrap().clone();
         assert_eq!(mem_acc.storage.len() as u64, max_slots);
     }
+
+    #[test]
+    fn reuses_flushed_cache() {
+        let path = std::env::temp_dir()
+            .join(format!("evm-adapters-fork-cache-{}.json", std::process::id()));
+        let (address, idx) = (Address::random(), H256::from_low_u64_be(1));
+        let account = MemoryAccount {
+            nonce: 1.into(),
+            balance: 100.into(),
+            code: vec![0x00],
+            storage: BTreeMap::from([(idx, H256::from_low_u64_be(2))]),
+        };
+        flush_cache_to(&MemCache::from([(address, account)]), 1, &path).unwrap();
+        assert!(load_cache_from(&path, 2).unwrap().is_empty());
+
+        // nothing can be fetched from the provider, so all values must come from the cache
+        let provider = Provider::<Http>::try_from("http://localhost:1").unwrap();
+        let cache = new_shared_cache(load_cache_from(&path, 1).unwrap());
+        let backend = SharedBackend::new(Arc::new(provider), cache, new_vicinity(), Some(1.into()));
+        let basic = backend.basic(address);
+        assert_eq!((basic.nonce, basic.balance), (1.into(), 100.into()));
+        assert_eq!(backend.code(address), vec![0x00]);
+        assert_eq!(backend.storage(address, idx), H256::from_low_u64_be(2));
+        fs::remove_file(path).unwrap();
+    }
 }
@@ -1,4 +1,6 @@
 pub mod cache;
-pub use cache::{new_shared_cache, MemCache, SharedBackend, SharedCache};
+pub use cache::{
+    flush_cache_to, load_cache_from, new_shared_cache, MemCache, SharedBackend, SharedCache,
+};
 pub mod rpc;
 pub use rpc::ForkMemoryBackend;
@@ -28,6 +28,8 @@ pub struct ForkMemoryBackend<B, M> {
     // TODO: This should probably be abstracted away into something that efficiently
     // also caches at disk etc.
     pub cache: RefCell<BTreeMap<H160, MemoryAccount>>,
+    /// The hashes of recent blocks fetched so far
+    block_hashes: RefCell<BTreeMap<U256, H256>>,
     /// The block to fetch data from.
     // This is an `Option` so that we can have less code churn in the functions below
     pin_block: Option<BlockId>,
@@ -57,6 +59,7 @@ where
             provider,
             backend,
             cache: RefCell::new(init_cache),
+            block_hashes: Default::default(),
             pin_block: pin_block.map(Into::into),
             pin_block_meta: block,
             chain_id,
@@ -77,7 +80,26 @@ where
     }
 
     fn block_hash(&self, number: U256) -> H256 {
-        self.backend.block_hash(number)
+        // like `BLOCKHASH`, only the 256 most recent blocks are available
+        let block_number = self.block_number();
+        if number >= block_number || block_number - number > U256::from(256) {
+            return H256::zero()
+        }
+        if let Some(hash) = self.block_hashes.borrow().get(&number) {
+            return *hash
+        }
+        match self.provider.get_block_hash(number.as_u64()) {
+            Ok(hash) => {
+                let hash = hash.unwrap_or_default();
+                self.block_hashes.borrow_mut().insert(number, hash);
+                hash
+            }
+            Err(err) => {
+                // not cached, so the hash is fetched again by the next lookup
+                tracing::warn!(?err, %number, "failed to get block hash");
+                H256::zero()
+            }
+        }
     }
 
     fn block_number(&self) -> U256 {
@@ -244,5 +266,11 @@ mod tests {
 
         // https://etherscan.io/block/13292465
         assert_eq!(res.as_u64(), 1632539668);
+
+        let provider = ethers::providers::MAINNET.provider();
+        let parent = rt.block_on(provider.get_block(13292464)).unwrap().unwrap();
+        assert_eq!(backend.block_hash(13292464.into()), parent.hash.unwrap());
+        assert_eq!(backend.block_hashes.borrow().len(), 1);
+        assert_eq!(backend.block_hash(13292465.into()), H256::zero());
     }
 }
@@ -4,13 +4,18 @@ pub use evm::*;
 mod forked_backend;
 pub use forked_backend::*;
 
+pub mod bundle;
 pub mod cheatcodes;
+pub mod coverage;
+pub mod diff;
+pub mod export;
+pub mod snapshot;
 pub mod state;
 
 use ethers::{
     abi::RawLog,
     providers::Middleware,
-    types::{Address, H160, H256, U256},
+    types::{Address, Log, H160, H256, U256},
 };
 
 use sputnik::{
@@ -69,6 +74,16 @@ pub trait SputnikExecutor<S> {
     fn tracing_enabled(&self) -> bool;
     fn debug_calls(&self) -> Vec<DebugArena>;
     fn all_logs(&self) -> Vec<String>;
+    /// Describes the arithmetic over/underflow which made the last transaction panic, if it
+    /// was recorded
+    fn arithmetic_overflow(&self) -> Option<String> {
+        None
+    }
+    /// Returns the step limit which the last transaction exceeded, if it was halted for
+    /// executing too many opcodes
+    fn step_limit_exceeded(&self) -> Option<usize> {
+        None
+    }
     fn gas_left(&self) -> U256;
     fn gas_used(&self) -> U256;
     fn gas_refund(&self) -> U256;
@@ -89,7 +104,17 @@ pub trait SputnikExecutor<S> {
         data: Vec<u8>,
         gas_limit: u64,
         access_list: Vec<(H160, Vec<H256>)>,
-    ) -> ExitReason;
+    ) -> (ExitReason, Vec<u8>);
+
+    fn transact_create2(
+        &mut self,
+        caller: H160,
+        value: U256,
+        data: Vec<u8>,
+        salt: H256,
+        gas_limit: u64,
+        access_list: Vec<(H160, Vec<H256>)>,
+    ) -> (ExitReason, Vec<u8>);
 
     fn create_address(&self, caller: CreateScheme) -> Address;
 
@@ -97,6 +122,12 @@ pub trait SputnikExecutor<S> {
     /// execution
     fn raw_logs(&self) -> Vec<RawLog>;
 
+    /// Returns the logs emitted during the previous VM execution, including the addresses of
+    /// the emitting contracts
+    fn emitted_logs(&self) -> Vec<Log> {
+        vec![]
+    }
+
     /// Gets a trace
     fn traces(&self) -> Vec<CallTraceArena> {
         vec![]
@@ -181,8 +212,20 @@ impl<'a, 'b, S: StackState<'a>, P: PrecompileSet> SputnikExecutor<S>
         data: Vec<u8>,
         gas_limit: u64,
         access_list: Vec<(H160, Vec<H256>)>,
-    ) -> ExitReason {
-        self.transact_create(caller, value, data, gas_limit, access_list).0
+    ) -> (ExitReason, Vec<u8>) {
+        self.transact_create(caller, value, data, gas_limit, access_list)
+    }
+
+    fn transact_create2(
+        &mut self,
+        caller: H160,
+        value: U256,
+        data: Vec<u8>,
+        salt: H256,
+        gas_limit: u64,
+        access_list: Vec<(H160, Vec<H256>)>,
+    ) -> (ExitReason, Vec<u8>) {
+        self.transact_create2(caller, value, data, salt, gas_limit, access_list)
     }
 
     fn create_address(&self, scheme: CreateScheme) -> Address {
@@ -0,0 +1,258 @@
+//! Checkpointing a subset of an executor's accounts
+use std::collections::BTreeMap;
+
+use ethers::types::{Address, H256, U256};
+use sputnik::{
+    backend::{Apply, Backend},
+    executor::stack::MemoryStackSubstate,
+};
+
+use crate::sputnik::{
+    cheatcodes::memory_stackstate_owned::MemoryStackStateOwned, Executor, SputnikExecutor,
+};
+
+/// The state of some accounts, as captured by
+/// [`snapshot_accounts`](Executor::snapshot_accounts)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountSnapshot {
+    pub accounts: BTreeMap<Address, AccountState>,
+}
+
+/// The full state of an account
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountState {
+    pub balance: U256,
+    pub nonce: U256,
+    pub code: Vec<u8>,
+    /// The storage slots which were written to during execution. All other slots hold the
+    /// backend's values.
+    pub storage: BTreeMap<H256, H256>,
+    /// Whether all of the account's storage was cleared during execution
+    pub reset_storage: bool,
+    /// Whether the account was deleted during execution
+    pub deleted: bool,
+}
+
+impl<'a, B: Backend, E> Executor<MemoryStackStateOwned<'a, B>, E>
+where
+    E: SputnikExecutor<MemoryStackStateOwned<'a, B>>,
+{
+    /// Captures the state of the given accounts only, which is much cheaper than cloning the
+    /// whole state when just a few accounts are going to be modified, e.g. on a large fork.
+    pub fn snapshot_accounts(&self, addresses: &[Address]) -> AccountSnapshot {
+        let state = self.executor.state();
+        let mut modified = modified_accounts(state);
+        let accounts = addresses
+            .iter()
+            .map(|address| {
+                let account = modified.remove(address).unwrap_or_else(|| {
+                    let basic = state.basic(*address);
+                    AccountState {
+                        balance: basic.balance,
+                        nonce: basic.nonce,
+                        code: state.code(*address),
+                        ..Default::default()
+                    }
+                });
+                (*address, account)
+            })
+            .collect();
+        AccountSnapshot { accounts }
+    }
+
+    /// Returns the full state of `address`, see [`AccountState`]
+    pub fn account(&self, address: Address) -> AccountState {
+        self.snapshot_accounts(&[address]).accounts.remove(&address).unwrap_or_default()
+    }
+
+    /// Restores the accounts of the snapshot to the state they had when it was taken. All other
+    /// accounts keep their current state, as do the logs and the accounts and storage slots
+    /// accessed so far (EIP-2929).
+    pub fn restore_accounts(&mut self, snapshot: AccountSnapshot) {
+        let state = self.executor.state_mut();
+        let mut accounts = modified_accounts(state);
+
+        // nonces can only be incremented and deletions and storage resets cannot be undone, so
+        // unless one of these happened since the snapshot, the accounts are restored in place
+        let in_place = snapshot.accounts.iter().all(|(address, account)| {
+            accounts.get(address).map_or(true, |current| {
+                !current.deleted &&
+                    current.nonce <= account.nonce &&
+                    (account.reset_storage || !current.reset_storage)
+            })
+        });
+        if in_place {
+            for (address, account) in &snapshot.accounts {
+                replay(
+                    &mut state.substate,
+                    &state.backend,
+                    *address,
+                    accounts.get(address),
+                    account,
+                );
+            }
+            return
+        }
+
+        // otherwise the substate is rebuilt from the backend, along with the metadata holding the
+        // accessed accounts and slots
+        accounts.extend(snapshot.accounts);
+        let mut substate = MemoryStackSubstate::new(state.substate.metadata().clone());
+        substate.logs_mut().extend_from_slice(state.substate.logs());
+        for (address, account) in accounts {
+            replay(&mut substate, &state.backend, address, None, &account);
+        }
+        state.substate = substate;
+    }
+}
+
+/// Returns all accounts the substate differs from the backend in
+fn modified_accounts<B: Backend>(
+    state: &MemoryStackStateOwned<'_, B>,
+) -> BTreeMap<Address, AccountState> {
+    let (applies, _) = state.substate.clone().deconstruct(&state.backend);
+    applies
+        .into_iter()
+        .map(|apply| match apply {
+            Apply::Modify { address, basic, code, storage, reset_storage } => {
+                let account = AccountState {
+                    balance: basic.balance,
+                    nonce: basic.nonce,
+                    code: code.unwrap_or_else(|| state.backend.code(address)),
+                    storage: storage.into_iter().collect(),
+                    reset_storage,
+                    deleted: false,
+                };
+                (address, account)
+            }
+            Apply::Delete { address } => {
+                (address, AccountState { deleted: true, ..Default::default() })
+            }
+        })
+        .collect()
+}
+
+/// Brings the account in the substate from its `current` state to `account`, where `None` stands
+/// for the backend's state. `current` must not have a higher nonce, nor be deleted or have its
+/// storage reset unless `account` does.
+fn replay<B: Backend>(
+    substate: &mut MemoryStackSubstate<'_>,
+    backend: &B,
+    address: Address,
+    current: Option<&AccountState>,
+    account: &AccountState,
+) {
+    if account.deleted {
+        substate.set_deleted(address);
+        return
+    }
+    if account.reset_storage && !current.map_or(false, |current| current.reset_storage) {
+        substate.reset_storage(address, backend);
+    }
+    if let Some(current) = current {
+        // slots which were written to after the account's state was captured
+        for slot in current.storage.keys().filter(|slot| !account.storage.contains_key(slot)) {
+            let value =
+                if account.reset_storage { H256::zero() } else { backend.storage(address, *slot) };
+            substate.set_storage(address, *slot, value);
+        }
+    }
+
+    substate.reset_balance(address, backend);
+    substate.deposit(address, account.balance, backend);
+    let mut nonce = current.map_or_else(|| backend.basic(address).nonce, |current| current.nonce);
+    while nonce < account.nonce {
+        substate.inc_nonce(address, backend);
+        nonce += U256::one();
+    }
+    let code = current.map_or_else(|| backend.code(address), |current| current.code.clone());
+    if account.code != code {
+        substate.set_code(address, account.code.clone(), backend);
+    }
+    for (slot, value) in &account.storage {
+        substate.set_storage(address, *slot, *value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sputnik::helpers::vm, test_helpers::COMPILED, Evm};
+
+    #[test]
+    fn restores_subset_of_accounts() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (greeter, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let (alice, bob) = (Address::random(), Address::random());
+        evm.set_balance(alice, 1.into());
+        evm.set_balance(bob, 1.into());
+
+        let snapshot = evm.snapshot_accounts(&[alice, greeter]);
+        let alice_before = evm.state().basic(alice);
+        let greeter_hash = evm.account_state_hash(greeter);
+
+        evm.set_balance(alice, 2.into());
+        evm.set_balance(bob, 2.into());
+        evm.call::<(), _, _>(alice, greeter, "gm()", (), 0.into(), None).unwrap();
+        assert_ne!(evm.state().basic(alice).nonce, alice_before.nonce);
+        assert_ne!(evm.account_state_hash(greeter), greeter_hash);
+
+        evm.restore_accounts(snapshot);
+        let alice_after = evm.state().basic(alice);
+        assert_eq!(alice_after.balance, alice_before.balance);
+        assert_eq!(alice_after.nonce, alice_before.nonce);
+        assert_eq!(evm.account_state_hash(greeter), greeter_hash);
+        assert!(!evm.code(greeter).is_empty());
+        let (greeting, _, _, _) = evm
+            .call::<String, _, _>(alice, greeter, "greeting()(string)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(greeting, "");
+
+        // accounts which were not part of the snapshot keep their changes
+        assert_eq!(evm.state().basic(bob).balance, 2.into());
+    }
+
+    #[test]
+    fn restores_storage_in_place() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (greeter, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+        let alice = Address::random();
+        let func = "greet(string)";
+        evm.call::<(), _, _>(alice, greeter, func, "hi".to_owned(), 0.into(), None).unwrap();
+
+        // only the greeter's storage changes, so it can be restored without rebuilding the
+        // substate
+        let snapshot = evm.snapshot_accounts(&[greeter]);
+        evm.call::<(), _, _>(alice, greeter, func, "yo".to_owned(), 0.into(), None).unwrap();
+        let nonce = evm.state().basic(alice).nonce;
+        evm.restore_accounts(snapshot);
+        assert_eq!(evm.state().basic(alice).nonce, nonce);
+
+        let (greeting, _, _, _) = evm
+            .call::<String, _, _>(alice, greeter, "greeting()(string)", (), 0.into(), None)
+            .unwrap();
+        assert_eq!(greeting, "hi");
+    }
+
+    #[test]
+    fn account() {
+        let mut evm = vm();
+        let compiled = COMPILED.find("Greeter").expect("could not find contract");
+        let (greeter, _, _, _) =
+            evm.deploy(Address::zero(), compiled.bytecode().unwrap().clone(), 0.into()).unwrap();
+
+        assert!(!evm.is_empty_account(greeter));
+        let account = evm.account(greeter);
+        assert_eq!(account.nonce, 1.into());
+        let runtime_code = compiled.bin_runtime.unwrap().clone().into_bytes().unwrap();
+        assert_eq!(ethers::utils::keccak256(&account.code), ethers::utils::keccak256(runtime_code));
+
+        let unused = Address::random();
+        assert!(evm.is_empty_account(unused));
+        assert_eq!(evm.account(unused), AccountState::default());
+    }
+}
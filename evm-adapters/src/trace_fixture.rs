@@ -0,0 +1,181 @@
+//! Golden files of call traces, for asserting that a transaction's call structure did not
+//! change
+use crate::call_tracing::CallTraceArena;
+use ethers::types::{Bytes, H160, U256};
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// The relative gas difference tolerated by [`assert_trace_matches_fixture`]
+pub const DEFAULT_GAS_TOLERANCE: f64 = 0.05;
+
+/// A call and its subcalls, in a stable JSON format which leaves out arena bookkeeping like
+/// node indices
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceFixture {
+    pub address: H160,
+    pub created: bool,
+    pub success: bool,
+    pub value: U256,
+    pub data: Bytes,
+    pub output: Bytes,
+    pub gas: u64,
+    pub calls: Vec<TraceFixture>,
+}
+
+impl TraceFixture {
+    /// Builds the fixture of the arena's entry call
+    pub fn new(arena: &CallTraceArena) -> Self {
+        Self::from_node(arena, arena.entry)
+    }
+
+    fn from_node(arena: &CallTraceArena, idx: usize) -> Self {
+        let node = &arena.arena[idx];
+        Self {
+            address: node.trace.addr,
+            created: node.trace.created,
+            success: node.trace.success,
+            value: node.trace.value,
+            data: node.trace.data.clone().into(),
+            output: node.trace.output.clone().into(),
+            gas: node.trace.cost,
+            calls: node.children.iter().map(|child| Self::from_node(arena, *child)).collect(),
+        }
+    }
+
+    /// Reads a fixture written by [`write`](Self::write)
+    pub fn read(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .wrap_err(format!("failed to read trace fixture \"{}\"", path.display()))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Writes the fixture as pretty printed JSON
+    pub fn write(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        Ok(fs::write(path, content)?)
+    }
+
+    /// Compares the trace against the expected one, returning a description of the first
+    /// difference found, if any. Gas may differ by up to `gas_tolerance`, relative to the
+    /// expected gas.
+    pub fn diff(&self, actual: &Self, gas_tolerance: f64) -> Option<String> {
+        self.diff_at("root", actual, gas_tolerance)
+    }
+
+    fn diff_at(&self, path: &str, actual: &Self, gas_tolerance: f64) -> Option<String> {
+        let mismatch = |field: &str, expected: String, actual: String| {
+            Some(format!("{}: expected {} {}, got {}", path, field, expected, actual))
+        };
+        let to_hex = |bytes: &Bytes| format!("0x{}", hex::encode(bytes));
+        if self.address != actual.address {
+            return mismatch(
+                "address",
+                format!("{:?}", self.address),
+                format!("{:?}", actual.address),
+            )
+        }
+        if self.created != actual.created {
+            return mismatch("created", self.created.to_string(), actual.created.to_string())
+        }
+        if self.success != actual.success {
+            return mismatch("success", self.success.to_string(), actual.success.to_string())
+        }
+        if self.value != actual.value {
+            return mismatch("value", self.value.to_string(), actual.value.to_string())
+        }
+        if self.data != actual.data {
+            return mismatch("data", to_hex(&self.data), to_hex(&actual.data))
+        }
+        if self.output != actual.output {
+            return mismatch("output", to_hex(&self.output), to_hex(&actual.output))
+        }
+        let gas_diff = (self.gas as f64 - actual.gas as f64).abs();
+        if gas_diff > self.gas as f64 * gas_tolerance {
+            return mismatch("gas", self.gas.to_string(), actual.gas.to_string())
+        }
+        if self.calls.len() != actual.calls.len() {
+            return mismatch(
+                "number of calls",
+                self.calls.len().to_string(),
+                actual.calls.len().to_string(),
+            )
+        }
+        self.calls.iter().zip(&actual.calls).enumerate().find_map(|(i, (expected, actual))| {
+            expected.diff_at(&format!("{}.calls[{}]", path, i), actual, gas_tolerance)
+        })
+    }
+}
+
+/// Asserts that the trace matches the golden trace at `fixture_path`, allowing gas to differ by
+/// up to [`DEFAULT_GAS_TOLERANCE`].
+///
+/// # Panics
+///
+/// If the fixture cannot be read or the traces diverge
+pub fn assert_trace_matches_fixture(trace: &CallTraceArena, fixture_path: impl AsRef<Path>) {
+    let path = fixture_path.as_ref();
+    let expected = TraceFixture::read(path).unwrap_or_else(|err| {
+        panic!("{:?}, it can be created with `TraceFixture::write`", err);
+    });
+    if let Some(diff) = expected.diff(&TraceFixture::new(trace), DEFAULT_GAS_TOLERANCE) {
+        panic!("trace does not match fixture \"{}\": {}", path.display(), diff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::call_tracing::{CallTrace, CallTraceNode};
+
+    fn arena() -> CallTraceArena {
+        let trace = |idx, depth, cost, output: Vec<u8>| CallTrace {
+            idx,
+            depth,
+            success: true,
+            addr: H160::from_low_u64_be(idx as u64 + 1),
+            cost,
+            output,
+            ..Default::default()
+        };
+        CallTraceArena {
+            arena: vec![
+                CallTraceNode {
+                    children: vec![1],
+                    trace: trace(0, 0, 1000, vec![]),
+                    ..Default::default()
+                },
+                CallTraceNode {
+                    parent: Some(0),
+                    idx: 1,
+                    trace: trace(1, 1, 100, vec![1]),
+                    ..Default::default()
+                },
+            ],
+            entry: 0,
+        }
+    }
+
+    #[test]
+    fn matches_fixture() {
+        let path = std::env::temp_dir()
+            .join(format!("evm-adapters-trace-fixture-{}.json", std::process::id()));
+        let mut arena = arena();
+        TraceFixture::new(&arena).write(&path).unwrap();
+        assert_eq!(TraceFixture::read(&path).unwrap(), TraceFixture::new(&arena));
+
+        // gas within the tolerance
+        arena.arena[0].trace.cost = 1040;
+        assert_trace_matches_fixture(&arena, &path);
+
+        arena.arena[1].trace.output = vec![2];
+        let expected = TraceFixture::read(&path).unwrap();
+        assert_eq!(
+            expected.diff(&TraceFixture::new(&arena), DEFAULT_GAS_TOLERANCE).unwrap(),
+            "root.calls[0]: expected output 0x01, got 0x02"
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,26 @@
+pragma solidity ^0.8.0;
+
+contract Arithmetic {
+    function add(uint256 a, uint256 b) public pure returns (uint256) {
+        return a + b;
+    }
+
+    function sub(uint256 a, uint256 b) public pure returns (uint256) {
+        return a - b;
+    }
+
+    function mul(uint256 a, uint256 b) public pure returns (uint256) {
+        return a * b;
+    }
+
+    function div(uint256 a, uint256 b) public pure returns (uint256) {
+        return a / b;
+    }
+
+    function sumThenSub(uint256 n, uint256 b) public pure returns (uint256 total) {
+        for (uint256 i = 0; i < n; i++) {
+            total += i;
+        }
+        return total - b;
+    }
+}
@@ -0,0 +1,7 @@
+pragma solidity ^0.8.7;
+
+contract BaseFee {
+    function baseFee() public view returns (uint256) {
+        return block.basefee;
+    }
+}
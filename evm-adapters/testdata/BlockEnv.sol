@@ -0,0 +1,7 @@
+pragma solidity ^0.8.7;
+
+contract BlockEnv {
+    function env() public view returns (uint256, uint256, address, uint256, uint256, uint256) {
+        return (block.number, block.timestamp, block.coinbase, block.basefee, tx.gasprice, block.chainid);
+    }
+}
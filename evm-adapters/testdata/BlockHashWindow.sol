@@ -0,0 +1,15 @@
+pragma solidity ^0.8.0;
+
+contract BlockHashWindow {
+    function check() public view {
+        require(blockhash(block.number) == 0, "current block has a hash");
+        require(blockhash(block.number - 1) != 0, "previous block has no hash");
+        require(blockhash(block.number - 256) != 0, "oldest block in window has no hash");
+        require(blockhash(block.number - 257) == 0, "block outside window has a hash");
+        require(blockhash(block.number - 1) != blockhash(block.number - 2), "hashes are not distinct");
+    }
+
+    function hash(uint256 number) public view returns (bytes32) {
+        return blockhash(number);
+    }
+}
@@ -0,0 +1,13 @@
+pragma solidity ^0.8.0;
+
+contract Branches {
+    uint256 public result;
+
+    function branch(uint256 x) external {
+        if (x < 10) {
+            result = x + 1;
+        } else {
+            result = x * 2;
+        }
+    }
+}
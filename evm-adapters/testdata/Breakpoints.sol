@@ -0,0 +1,19 @@
+pragma solidity ^0.8.0;
+
+interface BreakpointVm {
+    function breakpoint(string calldata) external;
+}
+
+contract Breakpoints {
+    BreakpointVm constant vm = BreakpointVm(0x7109709ECfa91a80626fF3989D68f67F5b1DD12D);
+
+    function run() public {
+        vm.breakpoint("start");
+        this.nested();
+        vm.breakpoint("end");
+    }
+
+    function nested() public {
+        vm.breakpoint("nested");
+    }
+}
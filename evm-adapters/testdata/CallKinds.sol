@@ -0,0 +1,41 @@
+pragma solidity ^0.8.0;
+
+contract CallTarget {
+    uint256 public x;
+
+    function set(uint256 _x) public payable {
+        x = _x;
+    }
+}
+
+contract CallKinds {
+    uint256 public x;
+    CallTarget public target;
+
+    constructor() {
+        target = new CallTarget();
+    }
+
+    function run() public payable {
+        target.set{value: 1}(1);
+        require(target.x() == 1);
+        (bool success, ) =
+            address(target).delegatecall(abi.encodeWithSelector(CallTarget.set.selector, 2));
+        require(success && x == 2);
+    }
+}
+
+contract RepeatedCalls {
+    CallTarget public target;
+
+    constructor() {
+        target = new CallTarget();
+    }
+
+    function run() public {
+        for (uint256 i = 0; i < 3; i++) {
+            target.set(i);
+        }
+        require(target.x() == 2);
+    }
+}
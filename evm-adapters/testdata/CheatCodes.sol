@@ -219,6 +219,19 @@ contract CheatCodes is DSTest {
         prank.bar(address(this));
     }
 
+    function testPrankKeepsOrigin() public {
+        Prank prank = new Prank();
+        address new_sender = address(1337);
+        address origin = tx.origin;
+        hevm.prank(new_sender);
+        prank.baz(new_sender, origin);
+        hevm.startPrank(new_sender);
+        prank.baz(new_sender, origin);
+        prank.baz(new_sender, origin);
+        hevm.stopPrank();
+        prank.baz(address(this), origin);
+    }
+
     function testPrankConstructor() public {
         address new_sender = address(1337);
         hevm.prank(new_sender);
@@ -0,0 +1,26 @@
+pragma solidity ^0.8.0;
+
+interface Hevm {
+    function warp(uint256) external;
+    function roll(uint256) external;
+}
+
+contract Clock {
+    Hevm constant hevm = Hevm(address(bytes20(uint160(uint256(keccak256('hevm cheat code'))))));
+
+    function warp(uint256 timestamp) public {
+        hevm.warp(timestamp);
+    }
+
+    function roll(uint256 number) public {
+        hevm.roll(number);
+    }
+
+    function timestamp() public view returns (uint256) {
+        return block.timestamp;
+    }
+
+    function number() public view returns (uint256) {
+        return block.number;
+    }
+}
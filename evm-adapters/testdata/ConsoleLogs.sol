@@ -3,6 +3,13 @@ pragma solidity ^0.8.0;
 import "./console.sol";
 
 contract ConsoleLogs {
+    function test_log_levels() public {
+        console.log("[info] deploying");
+        console.log("[debug] salt", 1);
+        console.log("[trace] entering loop");
+        console.log("done");
+    }
+
     function test_log() public {
 		console.log(0x1111111111111111111111111111111111111111);
 		console.log("Hi");
@@ -0,0 +1,10 @@
+pragma solidity ^0.8.0;
+
+// records failures without ds-test's `failed()`
+contract CustomAssertions {
+    bool public didRevert;
+
+    function recordFailure() public {
+        didRevert = true;
+    }
+}
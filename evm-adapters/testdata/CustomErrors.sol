@@ -0,0 +1,9 @@
+pragma solidity ^0.8.4;
+
+error InsufficientBalance(uint256 available, uint256 required);
+
+contract Vault {
+    function withdraw(uint256 amount) public pure {
+        revert InsufficientBalance(0, amount);
+    }
+}
@@ -0,0 +1,42 @@
+pragma solidity ^0.8.0;
+
+contract CycleProxy {
+    address public implementation;
+    // only read by `LoopingImplementation`, which shares the proxy's storage layout
+    address public next;
+
+    function setImplementation(address _implementation) public {
+        implementation = _implementation;
+    }
+
+    function setNext(address _next) public {
+        next = _next;
+    }
+
+    function run() public {
+        (bool success, bytes memory data) = implementation.delegatecall(abi.encodeWithSignature("run()"));
+        if (!success) {
+            assembly {
+                revert(add(data, 32), mload(data))
+            }
+        }
+    }
+}
+
+contract LoopingImplementation {
+    address private implementation;
+    address private next;
+
+    function run() public {
+        (bool success, bytes memory data) = next.delegatecall(abi.encodeWithSignature("run()"));
+        if (!success) {
+            assembly {
+                revert(add(data, 32), mload(data))
+            }
+        }
+    }
+}
+
+contract PlainImplementation {
+    function run() public {}
+}
@@ -0,0 +1,11 @@
+pragma solidity ^0.8.0;
+
+contract Emitter {
+    event Deposit(address indexed from, uint256 amount);
+    event Withdrawal(address indexed to, uint256 amount);
+
+    function emitBoth(uint256 amount) external {
+        emit Deposit(msg.sender, amount);
+        emit Withdrawal(msg.sender, amount * 2);
+    }
+}
@@ -0,0 +1,25 @@
+pragma solidity ^0.8.0;
+
+contract Worker {
+    uint256[] public values;
+
+    function cheap() public pure returns (uint256) {
+        return 1;
+    }
+
+    function expensive() public {
+        for (uint256 i = 0; i < 10; i++) {
+            values.push(i);
+        }
+    }
+}
+
+contract WorkerCaller {
+    Worker public worker = new Worker();
+
+    function run() public {
+        worker.cheap();
+        worker.cheap();
+        worker.expensive();
+    }
+}
@@ -0,0 +1,15 @@
+pragma solidity ^0.8.0;
+
+contract HeavySetup {
+    uint256[] public values;
+
+    function setUp() public {
+        for (uint256 i = 0; i < 100; i++) {
+            values.push(i);
+        }
+    }
+
+    function testValues() public view {
+        require(values.length == 100, "setUp did not run");
+    }
+}
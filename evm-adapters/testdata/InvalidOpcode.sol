@@ -0,0 +1,9 @@
+pragma solidity ^0.8.0;
+
+contract InvalidOpcode {
+    function fail() public pure {
+        assembly {
+            invalid()
+        }
+    }
+}
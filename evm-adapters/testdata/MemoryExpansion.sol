@@ -0,0 +1,9 @@
+pragma solidity ^0.8.0;
+
+contract MemoryExpansion {
+    function expand(uint256 offset) external pure {
+        assembly {
+            mstore(offset, 1)
+        }
+    }
+}
@@ -0,0 +1,27 @@
+pragma solidity ^0.8.0;
+
+contract NestedEmitterChild {
+    event Ping(uint256 n);
+
+    function ping(uint256 n) external {
+        emit Ping(n);
+    }
+
+    function pingAndRevert(uint256 n) external {
+        emit Ping(n);
+        revert("reverted");
+    }
+}
+
+contract NestedEmitter {
+    event Called(uint256 n);
+
+    NestedEmitterChild public child = new NestedEmitterChild();
+
+    function run() external {
+        emit Called(1);
+        child.ping(2);
+        try child.pingAndRevert(3) {} catch {}
+        emit Called(4);
+    }
+}
@@ -0,0 +1,7 @@
+pragma solidity ^0.8.0;
+
+contract Origin {
+    function origin() external view returns (address, address) {
+        return (tx.origin, msg.sender);
+    }
+}
@@ -0,0 +1,21 @@
+pragma solidity ^0.8.0;
+
+contract Permit {
+    bytes32 constant DOMAIN_TYPEHASH =
+        keccak256("EIP712Domain(uint256 chainId,address verifyingContract)");
+
+    mapping(address => uint256) public nonces;
+
+    function DOMAIN_SEPARATOR() public view returns (bytes32) {
+        return keccak256(abi.encode(DOMAIN_TYPEHASH, block.chainid, address(this)));
+    }
+
+    function digest(bytes32 structHash) public view returns (bytes32) {
+        return keccak256(abi.encodePacked("\x19\x01", DOMAIN_SEPARATOR(), structHash));
+    }
+
+    function permit(address owner, bytes32 structHash, uint8 v, bytes32 r, bytes32 s) public {
+        require(ecrecover(digest(structHash), v, r, s) == owner, "invalid signature");
+        nonces[owner]++;
+    }
+}
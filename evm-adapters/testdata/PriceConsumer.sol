@@ -0,0 +1,15 @@
+pragma solidity ^0.8.0;
+
+contract Oracle {
+    function price(uint256) external pure returns (uint256) {
+        return 1;
+    }
+}
+
+contract PriceConsumer {
+    Oracle public oracle = new Oracle();
+
+    function doublePrice(uint256 id) external view returns (uint256) {
+        return oracle.price(id) * 2;
+    }
+}
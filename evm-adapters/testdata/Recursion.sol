@@ -0,0 +1,10 @@
+pragma solidity ^0.8.0;
+
+contract Recursion {
+    uint256 public calls;
+
+    function recurse() public {
+        calls += 1;
+        this.recurse();
+    }
+}
@@ -0,0 +1,25 @@
+pragma solidity ^0.8.0;
+
+contract Thrower {
+    function fail() external pure {
+        revert("inner");
+    }
+}
+
+contract Rethrower {
+    Thrower public thrower = new Thrower();
+
+    function run() external view {
+        try thrower.fail() {} catch {
+            revert("rethrown");
+        }
+    }
+}
+
+contract Forwarder {
+    Rethrower public rethrower = new Rethrower();
+
+    function run() external view {
+        rethrower.run();
+    }
+}
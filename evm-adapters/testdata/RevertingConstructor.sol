@@ -0,0 +1,7 @@
+pragma solidity ^0.8.0;
+
+contract RevertingConstructor {
+    constructor() {
+        require(false, "constructor failed");
+    }
+}
@@ -0,0 +1,9 @@
+pragma solidity ^0.8.0;
+
+contract RevertingSetup {
+    function setUp() public pure {
+        revert("setup failed");
+    }
+
+    function testNothing() public {}
+}
@@ -0,0 +1,10 @@
+pragma solidity ^0.8.0;
+
+contract SignatureGated {
+    address constant SIGNER = address(uint160(0x1337));
+
+    function gated(bytes32 digest, uint8 v, bytes32 r, bytes32 s) public pure returns (bool) {
+        require(ecrecover(digest, v, r, s) == SIGNER, "invalid signature");
+        return true;
+    }
+}
@@ -0,0 +1,12 @@
+pragma solidity ^0.8.0;
+
+contract Splitter {
+    function split(address payable a, address payable b) external payable {
+        a.transfer(msg.value / 2);
+        b.transfer(msg.value - msg.value / 2);
+    }
+
+    function destroy(address payable beneficiary) external payable {
+        selfdestruct(beneficiary);
+    }
+}
@@ -0,0 +1,17 @@
+pragma solidity ^0.8.0;
+
+contract CheapReceiver {
+    event Received(uint256 amount);
+
+    receive() external payable {
+        emit Received(msg.value);
+    }
+}
+
+contract ExpensiveReceiver {
+    uint256 public received;
+
+    receive() external payable {
+        received += msg.value;
+    }
+}
@@ -0,0 +1,11 @@
+pragma solidity ^0.8.0;
+
+contract StorageClearing {
+    uint256[10] slots;
+
+    function clear() public {
+        for (uint256 i = 0; i < 10; i++) {
+            delete slots[i];
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pragma solidity ^0.8.0;
+
+contract StorageLayout {
+    uint256 a = 1;
+    // packed into slot 1
+    uint128 b = 2;
+    uint128 c = 3;
+    address owner = address(0x1337);
+}
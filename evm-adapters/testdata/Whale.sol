@@ -0,0 +1,19 @@
+pragma solidity ^0.8.0;
+
+contract WhaleToken {
+    mapping(address => uint256) public balanceOf;
+
+    constructor() {
+        balanceOf[msg.sender] = 1000;
+    }
+
+    function transfer(address to, uint256 amount) external returns (bool) {
+        balanceOf[msg.sender] -= amount;
+        balanceOf[to] += amount;
+        return true;
+    }
+}
+
+contract Whale {
+    WhaleToken public token = new WhaleToken();
+}
@@ -0,0 +1,244 @@
+//! Pluggable rendering of test results, traces and gas reports
+use crate::TestResult;
+use ansi_term::Colour;
+use evm_adapters::{call_tracing::CallTraceArena, gas_report::GasReport};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// All test results of a run, `contract -> (test name -> TestResult)`, as returned by
+/// [`MultiContractRunner::test`](crate::MultiContractRunner::test)
+pub type TestResults = BTreeMap<String, BTreeMap<String, TestResult>>;
+
+/// Renders the outcome of a test run in a specific output format.
+///
+/// This trait is object safe, so runners can pick a formatter at runtime, e.g. via
+/// `Box<dyn OutputFormatter>`.
+pub trait OutputFormatter {
+    /// Renders the result of the test `name` of `contract`
+    fn fmt_test_result(&self, contract: &str, name: &str, result: &TestResult) -> String;
+
+    /// Renders a call trace. `rendered` is its human readable form, as built by
+    /// [`CallTraceArena::construct_trace_string`].
+    fn fmt_trace(&self, trace: &CallTraceArena, rendered: &str) -> String;
+
+    /// Renders a finalized gas report
+    fn fmt_gas_report(&self, report: &GasReport) -> String;
+
+    /// Renders all results of a test run, by default one test result per line
+    fn fmt_test_results(&self, results: &TestResults) -> String {
+        let mut out = String::new();
+        for (contract, tests) in results {
+            for (name, result) in tests {
+                let _ = writeln!(out, "{}", self.fmt_test_result(contract, name, result));
+            }
+        }
+        out
+    }
+}
+
+/// Human readable output with ANSI colors, as printed by `forge test`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrettyFormatter;
+
+impl OutputFormatter for PrettyFormatter {
+    fn fmt_test_result(&self, _contract: &str, name: &str, result: &TestResult) -> String {
+        let status = if result.success {
+            Colour::Green.paint("[PASS]")
+        } else {
+            let txt = match (&result.reason, &result.counterexample) {
+                (Some(ref reason), Some(ref counterexample)) => {
+                    format!("[FAIL. Reason: {}. Counterexample: {}]", reason, counterexample)
+                }
+                (None, Some(ref counterexample)) => {
+                    format!("[FAIL. Counterexample: {}]", counterexample)
+                }
+                (Some(ref reason), None) => {
+                    format!("[FAIL. Reason: {}]", reason)
+                }
+                (None, None) => "[FAIL]".to_string(),
+            };
+
+            Colour::Red.paint(txt)
+        };
+
+        format!("{} {} {}", status, name, result.kind.gas_used())
+    }
+
+    fn fmt_trace(&self, _trace: &CallTraceArena, rendered: &str) -> String {
+        rendered.to_string()
+    }
+
+    fn fmt_gas_report(&self, report: &GasReport) -> String {
+        report.to_string()
+    }
+}
+
+/// Machine readable JSON output
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonFormatter;
+
+impl OutputFormatter for JsonFormatter {
+    fn fmt_test_result(&self, contract: &str, name: &str, result: &TestResult) -> String {
+        serde_json::json!({ "contract": contract, "name": name, "result": result }).to_string()
+    }
+
+    fn fmt_trace(&self, trace: &CallTraceArena, _rendered: &str) -> String {
+        serde_json::to_string(trace).expect("traces are always serializable")
+    }
+
+    fn fmt_gas_report(&self, report: &GasReport) -> String {
+        serde_json::to_string(report).expect("gas reports are always serializable")
+    }
+
+    /// Renders all results as a single JSON object
+    fn fmt_test_results(&self, results: &TestResults) -> String {
+        serde_json::to_string(results).expect("test results are always serializable")
+    }
+}
+
+/// JUnit XML output, which most CI systems can display natively
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JunitFormatter;
+
+impl OutputFormatter for JunitFormatter {
+    /// Renders a single `<testcase>` element
+    fn fmt_test_result(&self, contract: &str, name: &str, result: &TestResult) -> String {
+        let mut out = format!(
+            r#"<testcase classname="{}" name="{}">"#,
+            xml_escape(contract),
+            xml_escape(name)
+        );
+        if !result.success {
+            let mut message = result.reason.clone().unwrap_or_default();
+            if let Some(ref counterexample) = result.counterexample {
+                let _ = write!(message, " Counterexample: {}", counterexample);
+            }
+            let _ = write!(out, r#"<failure message="{}"/>"#, xml_escape(message.trim()));
+        }
+        let _ = write!(out, "<system-out>{}", xml_escape(&result.kind.gas_used().to_string()));
+        for log in &result.logs {
+            let _ = write!(out, "\n{}", xml_escape(log));
+        }
+        out.push_str("</system-out></testcase>");
+        out
+    }
+
+    fn fmt_trace(&self, _trace: &CallTraceArena, rendered: &str) -> String {
+        format!("<system-out>{}</system-out>", xml_escape(rendered))
+    }
+
+    /// JUnit has no notion of gas reports, so nothing is rendered
+    fn fmt_gas_report(&self, _report: &GasReport) -> String {
+        String::new()
+    }
+
+    /// Renders a complete JUnit document with one `<testsuite>` per contract
+    fn fmt_test_results(&self, results: &TestResults) -> String {
+        let count = |tests: &BTreeMap<String, TestResult>| {
+            (tests.len(), tests.values().filter(|result| !result.success).count())
+        };
+        let (tests, failures) = results
+            .values()
+            .map(count)
+            .fold((0, 0), |(tests, failures), (t, f)| (tests + t, failures + f));
+
+        let mut out = r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string();
+        let _ = write!(out, "\n<testsuites tests=\"{}\" failures=\"{}\">", tests, failures);
+        for (contract, results) in results {
+            let (tests, failures) = count(results);
+            let _ = write!(
+                out,
+                "\n  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+                xml_escape(contract),
+                tests,
+                failures
+            );
+            for (name, result) in results {
+                let _ = write!(out, "\n    {}", self.fmt_test_result(contract, name, result));
+            }
+            out.push_str("\n  </testsuite>");
+        }
+        out.push_str("\n</testsuites>\n");
+        out
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // ANSI escape codes, e.g. of rendered traces, are invalid in XML 1.0
+            c if c.is_control() && c != '\n' && c != '\t' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestKind;
+
+    fn result(success: bool, reason: Option<&str>) -> TestResult {
+        TestResult {
+            success,
+            reason: reason.map(str::to_string),
+            gas_used: 100,
+            counterexample: None,
+            logs: vec!["a < b".to_string()],
+            kind: TestKind::Standard(100),
+            traces: None,
+            identified_contracts: None,
+            debug_calls: None,
+            labeled_addresses: Default::default(),
+        }
+    }
+
+    fn results() -> TestResults {
+        let tests = BTreeMap::from([
+            ("testFail()".to_string(), result(false, Some("not \"1\""))),
+            ("testPass()".to_string(), result(true, None)),
+        ]);
+        BTreeMap::from([("GreeterTest".to_string(), tests)])
+    }
+
+    #[test]
+    fn formatters_are_object_safe() {
+        let formatters: Vec<Box<dyn OutputFormatter>> =
+            vec![Box::new(PrettyFormatter), Box::new(JsonFormatter), Box::new(JunitFormatter)];
+        for formatter in formatters {
+            assert!(!formatter.fmt_test_results(&results()).is_empty());
+        }
+    }
+
+    #[test]
+    fn json_results() {
+        let json: serde_json::Value =
+            serde_json::from_str(&JsonFormatter.fmt_test_results(&results())).unwrap();
+        assert_eq!(json["GreeterTest"]["testPass()"]["success"], true);
+        assert_eq!(json["GreeterTest"]["testFail()"]["reason"], "not \"1\"");
+    }
+
+    #[test]
+    fn junit_results() {
+        let xml = JunitFormatter.fmt_test_results(&results());
+        assert_eq!(
+            xml,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="2" failures="1">
+  <testsuite name="GreeterTest" tests="2" failures="1">
+    <testcase classname="GreeterTest" name="testFail()"><failure message="not &quot;1&quot;"/><system-out>(gas: 100)
+a &lt; b</system-out></testcase>
+    <testcase classname="GreeterTest" name="testPass()"><system-out>(gas: 100)
+a &lt; b</system-out></testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
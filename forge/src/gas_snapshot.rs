@@ -0,0 +1,160 @@
+//! Reading and writing of `.gas-snapshot` files, as emitted by `forge snapshot`
+use crate::TestKindGas;
+use eyre::Context;
+use std::{
+    fmt::{self, Write},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+/// A single line of a gas snapshot file, which has the form
+/// `Test:testDeposit() (gas: 58804)` or `Test:testFuzz(uint256) (runs: 256, μ: 100, ~: 200)`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SnapshotEntry {
+    pub contract_name: String,
+    pub signature: String,
+    pub gas_used: TestKindGas,
+}
+
+impl fmt::Display for SnapshotEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{} {}", self.contract_name, self.signature, self.gas_used)
+    }
+}
+
+impl FromStr for SnapshotEntry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_entry(s).ok_or_else(|| format!("Could not extract Snapshot Entry for {}", s))
+    }
+}
+
+fn parse_entry(s: &str) -> Option<SnapshotEntry> {
+    let (contract_name, rest) = s.trim().split_once(':')?;
+    // the gas section is the last parenthesized group, everything before it is the signature
+    let gas_start = rest.rfind('(')?;
+    let (signature, gas) = rest.split_at(gas_start);
+    let gas = gas.strip_prefix('(')?.trim_end().strip_suffix(')')?.trim();
+
+    let signature = signature.trim();
+    if contract_name.is_empty() || !signature.ends_with(')') {
+        return None
+    }
+
+    let gas_used = if gas.starts_with("runs") {
+        let (mut runs, mut mean, mut median) = (None, None, None);
+        for field in gas.split(',') {
+            let (key, value) = field.split_once(':')?;
+            let value = value.trim();
+            match key.trim() {
+                "runs" => runs = Some(value.parse().ok()?),
+                "μ" => mean = Some(value.parse().ok()?),
+                "~" => median = Some(value.parse().ok()?),
+                _ => return None,
+            }
+        }
+        TestKindGas::Fuzz { runs: runs?, mean: mean?, median: median? }
+    } else {
+        TestKindGas::Standard(gas.strip_prefix("gas:").unwrap_or(gas).trim().parse().ok()?)
+    };
+
+    Some(SnapshotEntry {
+        contract_name: contract_name.to_string(),
+        signature: signature.to_string(),
+        gas_used,
+    })
+}
+
+/// Reads a list of snapshot entries from a snapshot file, skipping empty lines
+pub fn read_gas_snapshot_file(path: impl AsRef<Path>) -> eyre::Result<Vec<SnapshotEntry>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .wrap_err(format!("failed to read snapshot file \"{}\"", path.display()))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| SnapshotEntry::from_str(line).map_err(|err| eyre::eyre!("{}", err)))
+        .collect()
+}
+
+/// Writes the snapshot entries to a snapshot file, one entry per line
+pub fn write_gas_snapshot_file(
+    path: impl AsRef<Path>,
+    entries: impl IntoIterator<Item = SnapshotEntry>,
+) -> eyre::Result<()> {
+    let mut out = String::new();
+    for entry in entries {
+        writeln!(out, "{}", entry)?;
+    }
+    Ok(fs::write(path, out)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_basic_snapshot_entry() {
+        let s = "Test:deposit() (gas: 7222)";
+        let entry = SnapshotEntry::from_str(s).unwrap();
+        assert_eq!(
+            entry,
+            SnapshotEntry {
+                contract_name: "Test".to_string(),
+                signature: "deposit()".to_string(),
+                gas_used: TestKindGas::Standard(7222)
+            }
+        );
+    }
+
+    #[test]
+    fn can_parse_fuzz_snapshot_entry() {
+        let s = "Test:deposit() (runs: 256, μ: 100, ~:200)";
+        let entry = SnapshotEntry::from_str(s).unwrap();
+        assert_eq!(
+            entry,
+            SnapshotEntry {
+                contract_name: "Test".to_string(),
+                signature: "deposit()".to_string(),
+                gas_used: TestKindGas::Fuzz { runs: 256, median: 200, mean: 100 }
+            }
+        );
+    }
+
+    #[test]
+    fn can_parse_loose_snapshot_entry() {
+        let entry = SnapshotEntry::from_str("Test:transfer(address,uint256)(7222)  ").unwrap();
+        assert_eq!(entry.signature, "transfer(address,uint256)");
+        assert_eq!(entry.gas_used, TestKindGas::Standard(7222));
+        assert!(SnapshotEntry::from_str("deposit() (gas: 7222)").is_err());
+        assert!(SnapshotEntry::from_str("Test:deposit() (gas: lots)").is_err());
+    }
+
+    #[test]
+    fn can_roundtrip_snapshot_file() {
+        let entries = vec![
+            SnapshotEntry {
+                contract_name: "GreeterTest".to_string(),
+                signature: "testGreeting()".to_string(),
+                gas_used: TestKindGas::Standard(58804),
+            },
+            SnapshotEntry {
+                contract_name: "GreeterTest".to_string(),
+                signature: "testFuzzGreeting(string)".to_string(),
+                gas_used: TestKindGas::Fuzz { runs: 256, mean: 60123, median: 59884 },
+            },
+        ];
+
+        let path = std::env::temp_dir()
+            .join(format!("forge-roundtrip-{}.gas-snapshot", std::process::id()));
+        write_gas_snapshot_file(&path, entries.clone()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "GreeterTest:testGreeting() (gas: 58804)\nGreeterTest:testFuzzGreeting(string) (runs: 256, μ: 60123, ~: 59884)\n"
+        );
+        assert_eq!(read_gas_snapshot_file(&path).unwrap(), entries);
+        fs::remove_file(path).unwrap();
+    }
+}
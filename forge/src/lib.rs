@@ -1,5 +1,9 @@
 mod runner;
-pub use runner::{ContractRunner, TestKind, TestKindGas, TestResult};
+pub use runner::{ContractRunner, TestHook, TestKind, TestKindGas, TestResult};
+
+pub mod format;
+
+pub mod gas_snapshot;
 
 mod multi_runner;
 pub use multi_runner::{MultiContractRunner, MultiContractRunnerBuilder};
@@ -147,6 +147,14 @@ impl TestKind {
 type MaybeExecutionInfo<'a> =
     Option<(&'a BTreeMap<[u8; 4], Function>, &'a BTreeMap<H256, Event>, &'a Abi)>;
 
+/// A hook which is run around each test of a [`ContractRunner`], with the test's EVM and the
+/// address of the deployed test contract.
+///
+/// Tests are run in parallel, so hooks must be `Sync` and need interior mutability to keep
+/// state across tests.
+pub type TestHook<'a, B> =
+    Box<dyn for<'r> Fn(&mut TestSputnikVM<'r, B>, Address) + Send + Sync + 'a>;
+
 pub struct ContractRunner<'a, B> {
     // EVM Config Options
     /// The options used to instantiate a new EVM.
@@ -169,6 +177,14 @@ pub struct ContractRunner<'a, B> {
     pub execution_info: MaybeExecutionInfo<'a>,
     /// library contracts to be deployed before this contract
     pub predeploy_libs: &'a [ethers::prelude::Bytes],
+
+    /// Runs before each test, after the test contract was deployed and `setUp()` was called.
+    /// Every test runs in a fresh EVM, so changes made by the hook do not leak into other tests.
+    /// Fuzz tests call the hook once before fuzzing, not for every case.
+    pub before_each: Option<TestHook<'a, B>>,
+    /// Runs after each test with the state the test left behind. Not called for tests whose
+    /// `setUp()` failed, like `before_each`.
+    pub after_each: Option<TestHook<'a, B>>,
 }
 
 impl<'a, B: Backend> ContractRunner<'a, B> {
@@ -192,6 +208,8 @@ impl<'a, B: Backend> ContractRunner<'a, B> {
             sender: sender.unwrap_or_default(),
             execution_info,
             predeploy_libs,
+            before_each: None,
+            after_each: None,
         }
     }
 }
@@ -347,6 +365,10 @@ impl<'a, B: Backend + Clone + Send + Sync> ContractRunner<'a, B> {
             logs.extend_from_slice(&setup_logs);
         }
 
+        if let Some(ref hook) = self.before_each {
+            hook(&mut evm, address);
+        }
+
         let (status, reason, gas_used, logs) = match evm.call::<(), _, _>(
             self.sender,
             address,
@@ -360,7 +382,7 @@ impl<'a, B: Backend + Clone + Send + Sync> ContractRunner<'a, B> {
                 (status, None, gas_used, logs)
             }
             Err(err) => match err {
-                EvmError::Execution { reason, gas_used, logs: execution_logs } => {
+                EvmError::Execution { reason, gas_used, logs: execution_logs, .. } => {
                     logs.extend(execution_logs);
                     // add reverted logs
                     logs.extend(evm.all_logs());
@@ -382,6 +404,9 @@ impl<'a, B: Backend + Clone + Send + Sync> ContractRunner<'a, B> {
         );
 
         let success = evm.check_success(address, &status, should_fail);
+        if let Some(ref hook) = self.after_each {
+            hook(&mut evm, address);
+        }
         let duration = Instant::now().duration_since(start);
         tracing::debug!(?duration, %success, %gas_used);
 
@@ -457,6 +482,10 @@ impl<'a, B: Backend + Clone + Send + Sync> ContractRunner<'a, B> {
             }
         }
 
+        if let Some(ref hook) = self.before_each {
+            hook(&mut evm, address);
+        }
+
         let mut logs = init_logs;
 
         let prev = evm.set_tracing_enabled(false);
@@ -492,6 +521,10 @@ impl<'a, B: Backend + Clone + Send + Sync> ContractRunner<'a, B> {
             }
         }
 
+        if let Some(ref hook) = self.after_each {
+            hook(evm, address);
+        }
+
         let success = test_error.is_none();
         let mut counterexample = None;
         let mut reason = None;
@@ -617,6 +650,39 @@ mod tests {
             super::test_runner(compiled);
         }
 
+        #[test]
+        fn test_hooks() {
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let compiled = COMPILED.find("GreeterTest").expect("could not find contract");
+            let (_, code, _) = compiled.into_parts_or_default();
+            let mut libs = vec![];
+            let mut runner = runner(compiled.abi.as_ref().unwrap(), code, &mut libs);
+
+            let account = Address::repeat_byte(0x11);
+            let (before, after) = (AtomicUsize::new(0), AtomicUsize::new(0));
+            runner.before_each =
+                Some(Box::new(|evm: &mut TestSputnikVM<'_, MemoryBackend<'_>>, _: Address| {
+                    evm.set_balance(account, 1.into());
+                    before.fetch_add(1, Ordering::SeqCst);
+                }));
+            runner.after_each = Some(Box::new(
+                |evm: &mut TestSputnikVM<'_, MemoryBackend<'_>>, address: Address| {
+                    // the state of the hook and the test are both visible
+                    assert_eq!(evm.state().basic(account).balance, 1.into());
+                    assert!(!evm.code(address).is_empty());
+                    after.fetch_add(1, Ordering::SeqCst);
+                },
+            ));
+
+            let results = runner.run_tests(&Filter::matches_all(), None, None).unwrap();
+            assert_eq!(results.len(), 3);
+            assert!(results.values().all(|result| result.success));
+            drop(runner);
+            assert_eq!(before.into_inner(), 3);
+            assert_eq!(after.into_inner(), 3);
+        }
+
         #[test]
         fn test_function_overriding() {
             let compiled = COMPILED.find("GreeterTest").expect("could not find contract");
@@ -186,6 +186,12 @@ impl<'a> IntoFunction for &'a str {
 /// Given a gas value and a calldata array, it subtracts the calldata cost from the
 /// gas value, as well as the 21k base gas cost for all transactions.
 pub fn remove_extra_costs(gas: U256, calldata: &[u8]) -> U256 {
+    gas.saturating_sub(intrinsic_gas(calldata).into())
+}
+
+/// Returns the gas a call transaction with the given calldata is charged before execution: the
+/// 21k base gas cost for all transactions plus the calldata cost
+pub fn intrinsic_gas(calldata: &[u8]) -> u64 {
     let mut calldata_cost = 0;
     for i in calldata {
         if *i != 0 {
@@ -197,7 +203,7 @@ pub fn remove_extra_costs(gas: U256, calldata: &[u8]) -> U256 {
             calldata_cost += 4;
         }
     }
-    gas.saturating_sub(calldata_cost.into()).saturating_sub(BASE_TX_COST.into())
+    BASE_TX_COST + calldata_cost
 }
 
 /// Flattens a group of contracts into maps of all events and functions
@@ -234,8 +240,26 @@ pub fn flatten_known_contracts(
     (flattened_funcs, flattened_events, errors_abi)
 }
 
+/// The selector of `Panic(uint256)`
+const PANIC_SELECTOR: [u8; 4] = [78, 72, 123, 113];
+
+/// The panic code solc uses for arithmetic over/underflows
+const ARITHMETIC_PANIC_CODE: u8 = 0x11;
+
+/// Returns true if the revert data is a `Panic(0x11)`, i.e. a checked arithmetic operation
+/// over/underflowed
+pub fn is_arithmetic_panic(retdata: &[u8]) -> bool {
+    retdata.len() == 36 &&
+        retdata[..4] == PANIC_SELECTOR &&
+        retdata[4..35].iter().all(|b| *b == 0) &&
+        retdata[35] == ARITHMETIC_PANIC_CODE
+}
+
 /// Given an ABI encoded error string with the function signature `Error(string)`, it decodes
 /// it and returns the revert error message.
+///
+/// `Error(string)` and `Panic(uint256)` reverts are always decoded, the ABI is only consulted
+/// for custom errors.
 pub fn decode_revert(error: &[u8], maybe_abi: Option<&Abi>) -> Result<String> {
     if error.len() >= 4 {
         match error[0..4] {
@@ -432,6 +456,43 @@ pub fn encode_args(func: &Function, args: &[impl AsRef<str>]) -> Result<Vec<u8>>
     Ok(func.encode_input(&tokens)?)
 }
 
+/// ABI encodes the tokens in the non-standard packed mode, like Solidity's `abi.encodePacked`.
+///
+/// Static types are encoded in place without padding, except for integers which are always
+/// encoded with 32 bytes since tokens do not carry their bit size, e.g. pass a `uint16` as
+/// [`Token::FixedBytes`] of length 2 instead. Dynamic types are encoded without their length.
+/// Array elements are padded to 32 bytes. Tuples and arrays of dynamic types are not supported,
+/// like in Solidity.
+///
+/// Packed encodings are ambiguous (e.g. `("a", "bc")` and `("ab", "c")` encode the same), so
+/// they cannot be decoded and are only useful for hashing.
+pub fn encode_packed(tokens: &[Token]) -> Result<Bytes> {
+    let mut out = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Address(address) => out.extend_from_slice(address.as_bytes()),
+            Token::Uint(_) | Token::Int(_) => out.extend(abi::encode(&[token.clone()])),
+            Token::Bool(b) => out.push(*b as u8),
+            Token::FixedBytes(bytes) | Token::Bytes(bytes) => out.extend_from_slice(bytes),
+            Token::String(s) => out.extend_from_slice(s.as_bytes()),
+            Token::Array(tokens) | Token::FixedArray(tokens) => {
+                for token in tokens {
+                    match token {
+                        Token::Address(_) |
+                        Token::Uint(_) |
+                        Token::Int(_) |
+                        Token::Bool(_) |
+                        Token::FixedBytes(_) => out.extend(abi::encode(&[token.clone()])),
+                        _ => eyre::bail!("cannot packed encode an array of {:?}", token),
+                    }
+                }
+            }
+            Token::Tuple(_) => eyre::bail!("cannot packed encode a tuple"),
+        }
+    }
+    Ok(out.into())
+}
+
 /// Fetches a function signature given the selector using 4byte.directory
 pub async fn fourbyte(selector: &str) -> Result<Vec<(String, i32)>> {
     #[derive(Deserialize)]
@@ -590,6 +651,33 @@ pub async fn pretty_calldata(calldata: impl AsRef<str>, offline: bool) -> Result
     Ok(possible_info)
 }
 
+/// Computes the bloom filter of a receipt with the given logs, as specified in the yellow paper:
+/// the address and all topics of every log are added to the 2048-bit filter
+pub fn logs_bloom(logs: &[Log]) -> Bloom {
+    let mut bloom = Bloom::zero();
+    for log in logs {
+        bloom.accrue(abi::ethereum_types::BloomInput::Raw(log.address.as_bytes()));
+        for topic in &log.topics {
+            bloom.accrue(abi::ethereum_types::BloomInput::Raw(topic.as_bytes()));
+        }
+    }
+    bloom
+}
+
+/// Decodes the logs which were emitted by one of the events of `abi`, returning the name of
+/// each log's event along with its parameters, indexed ones included, in declaration order.
+/// Logs which match none of the events are skipped.
+pub fn decode_logs(logs: &[abi::RawLog], abi: &Abi) -> Vec<(String, Vec<Token>)> {
+    logs.iter()
+        .filter_map(|log| {
+            abi.events().find_map(|event| {
+                let decoded = event.parse_log(log.clone()).ok()?;
+                Some((event.name.clone(), decoded.params.into_iter().map(|p| p.value).collect()))
+            })
+        })
+        .collect()
+}
+
 pub fn abi_decode(sig: &str, calldata: &str, input: bool) -> Result<Vec<Token>> {
     let func = IntoFunction::into(sig);
     let calldata = calldata.strip_prefix("0x").unwrap_or(calldata);
@@ -1175,6 +1263,100 @@ mod tests {
         assert_eq!(sigs[0].0, "canCall(address,address,bytes4)".to_string());
     }
 
+    #[test]
+    fn test_logs_bloom() {
+        assert_eq!(logs_bloom(&[]), Bloom::zero());
+
+        let log = Log {
+            address: "0xef2d6d194084c2de36e0dabfce45d046b37d1106".parse().unwrap(),
+            topics: vec!["0x02c69be41d0b7e40352fc85be1cd65eb03d40ef8427a0ca4596b1ead9a00e9fc"
+                .parse()
+                .unwrap()],
+            ..Default::default()
+        };
+        let mut expected = Bloom::zero();
+        for (i, byte) in
+            [(20, 0x10), (99, 0x02), (100, 0x02), (123, 0x08), (128, 0x10), (155, 0x10)]
+        {
+            expected.0[i] = byte;
+        }
+        assert_eq!(logs_bloom(&[log.clone()]), expected);
+        // the data is not part of the filter
+        let log = Log { data: vec![1, 2, 3].into(), ..log };
+        assert_eq!(logs_bloom(&[log.clone(), log]), expected);
+    }
+
+    #[test]
+    fn test_is_arithmetic_panic() {
+        let mut retdata = PANIC_SELECTOR.to_vec();
+        retdata.extend_from_slice(H256::from_low_u64_be(0x11).as_bytes());
+        assert!(is_arithmetic_panic(&retdata));
+
+        retdata[35] = 0x12;
+        assert!(!is_arithmetic_panic(&retdata));
+    }
+
+    #[test]
+    fn test_decode_revert() {
+        let mut revert = ethers::utils::id("Error(string)").to_vec();
+        revert.extend(abi::encode(&[Token::String("not allowed".to_string())]));
+        let unauthorized = ethers::utils::id("Unauthorized(address)").to_vec();
+        let abi: Abi = serde_json::from_str(
+            r#"[{"type":"error","name":"Unauthorized","inputs":[{"name":"caller","type":"address"}]}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(decode_revert(&revert, None).unwrap(), "not allowed");
+        assert_eq!(decode_revert(&revert, Some(&abi)).unwrap(), "not allowed");
+
+        let mut custom = unauthorized;
+        custom.extend(abi::encode(&[Token::Address(Address::zero())]));
+        assert!(decode_revert(&custom, Some(&abi)).unwrap().starts_with("Unauthorized("));
+        assert!(decode_revert(&custom, None).is_err());
+    }
+
+    #[test]
+    fn test_encode_packed() {
+        // abi.encodePacked(address(1), uint256(1), int256(-1), true, "foo", bytes2(0xabcd))
+        let tokens = [
+            Token::Address(Address::from_low_u64_be(1)),
+            Token::Uint(1.into()),
+            Token::Int(U256::MAX),
+            Token::Bool(true),
+            Token::String("foo".to_string()),
+            Token::FixedBytes(vec![0xab, 0xcd]),
+        ];
+        assert_eq!(
+            hex::encode(encode_packed(&tokens).unwrap()),
+            format!(
+                "{}{}{}01666f6fabcd",
+                "0000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "ff".repeat(32),
+            )
+        );
+
+        // abi.encodePacked(bytes("a"), [address(1), address(2)])
+        let tokens = [
+            Token::Bytes(b"a".to_vec()),
+            Token::FixedArray(vec![
+                Token::Address(Address::from_low_u64_be(1)),
+                Token::Address(Address::from_low_u64_be(2)),
+            ]),
+        ];
+        assert_eq!(
+            hex::encode(encode_packed(&tokens).unwrap()),
+            format!(
+                "61{}{}",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+            )
+        );
+
+        assert!(encode_packed(&[Token::Array(vec![Token::String("a".to_string())])]).is_err());
+        assert!(encode_packed(&[Token::Tuple(vec![])]).is_err());
+    }
+
     #[test]
     #[cfg(any(target_os = "linux", target_os = "macos"))]
     fn abi2solidity() {